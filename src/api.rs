@@ -1,12 +1,12 @@
 //! API server for email-sleuth.
 
+use crate::error::Result;
 use crate::models::{Contact, ProcessingResult};
-use crate::processor::process_record;
 use crate::sleuth::EmailSleuth;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::Semaphore;
-use warp::{http::StatusCode, Filter, Rejection, Reply};
-use serde::{Deserialize, Serialize};
+use warp::{Filter, Rejection, Reply, http::StatusCode};
 
 /// API response structure
 #[derive(Serialize, Deserialize)]
@@ -31,24 +31,23 @@ struct BatchResponse {
     results: Vec<ProcessingResult>,
 }
 
-/// Start the API server
-pub async fn start_api_server(port: u16) -> Result<(), Box<dyn std::error::Error>> {
-    let sleuth = Arc::new(EmailSleuth::new().await?);
+/// Start the API server, serving requests with the given `EmailSleuth`.
+pub async fn start_api_server(sleuth: Arc<EmailSleuth>, port: u16) -> Result<()> {
     let sleuth_filter = warp::any().map(move || sleuth.clone());
-    
+
     // Limit concurrent requests
     let semaphore = Arc::new(Semaphore::new(10));
     let semaphore_filter = warp::any().map(move || semaphore.clone());
-    
+
     // Health check endpoint
-    let health = warp::path("health")
-        .and(warp::get())
-        .map(|| warp::reply::json(&ApiResponse {
+    let health = warp::path("health").and(warp::get()).map(|| {
+        warp::reply::json(&ApiResponse {
             success: true,
             message: "Email Sleuth API is running".to_string(),
             result: None,
-        }));
-    
+        })
+    });
+
     // Single contact verification endpoint
     let verify = warp::path("verify")
         .and(warp::post())
@@ -56,7 +55,7 @@ pub async fn start_api_server(port: u16) -> Result<(), Box<dyn std::error::Error
         .and(sleuth_filter.clone())
         .and(semaphore_filter.clone())
         .and_then(handle_verify);
-    
+
     // Batch verification endpoint
     let batch = warp::path("batch")
         .and(warp::post())
@@ -64,16 +63,15 @@ pub async fn start_api_server(port: u16) -> Result<(), Box<dyn std::error::Error
         .and(sleuth_filter.clone())
         .and(semaphore_filter.clone())
         .and_then(handle_batch);
-    
+
     // Serve static files for the UI
-    let ui = warp::path("ui")
-        .and(warp::fs::dir("ui"));
-    
+    let ui = warp::path("ui").and(warp::fs::dir("ui"));
+
     // Redirect root to UI
     let root = warp::path::end()
         .and(warp::get())
         .map(|| warp::redirect::temporary(warp::http::Uri::from_static("/ui")));
-    
+
     // Combine all routes
     let routes = health
         .or(verify)
@@ -81,10 +79,10 @@ pub async fn start_api_server(port: u16) -> Result<(), Box<dyn std::error::Error
         .or(ui)
         .or(root)
         .with(warp::cors().allow_any_origin());
-    
+
     tracing::info!("Starting API server on port {}", port);
     warp::serve(routes).run(([0, 0, 0, 0], port)).await;
-    
+
     Ok(())
 }
 
@@ -93,12 +91,15 @@ async fn handle_verify(
     contact: Contact,
     sleuth: Arc<EmailSleuth>,
     semaphore: Arc<Semaphore>,
-) -> Result<impl Reply, Rejection> {
-    let _permit = semaphore.acquire().await.map_err(|_| warp::reject::custom(ApiError))?;
-    
+) -> std::result::Result<impl Reply, Rejection> {
+    let _permit = semaphore
+        .acquire()
+        .await
+        .map_err(|_| warp::reject::custom(ApiError))?;
+
     tracing::info!("Processing single contact verification request");
-    let result = process_record(sleuth, contact).await;
-    
+    let result = sleuth.process_record(contact).await;
+
     Ok(warp::reply::json(&ApiResponse {
         success: true,
         message: "Contact processed successfully".to_string(),
@@ -111,17 +112,20 @@ async fn handle_batch(
     batch: BatchRequest,
     sleuth: Arc<EmailSleuth>,
     semaphore: Arc<Semaphore>,
-) -> Result<impl Reply, Rejection> {
+) -> std::result::Result<impl Reply, Rejection> {
     tracing::info!("Processing batch of {} contacts", batch.contacts.len());
-    
+
     let mut results = Vec::with_capacity(batch.contacts.len());
-    
+
     for contact in batch.contacts {
-        let _permit = semaphore.acquire().await.map_err(|_| warp::reject::custom(ApiError))?;
-        let result = process_record(sleuth.clone(), contact).await;
+        let _permit = semaphore
+            .acquire()
+            .await
+            .map_err(|_| warp::reject::custom(ApiError))?;
+        let result = sleuth.process_record(contact).await;
         results.push(result);
     }
-    
+
     Ok(warp::reply::json(&BatchResponse {
         success: true,
         message: format!("Processed {} contacts", results.len()),
@@ -136,7 +140,7 @@ struct ApiError;
 impl warp::reject::Reject for ApiError {}
 
 /// Handle API rejections
-pub async fn handle_rejection(err: Rejection) -> Result<impl Reply, Rejection> {
+pub async fn handle_rejection(err: Rejection) -> std::result::Result<impl Reply, Rejection> {
     if err.is_not_found() {
         Ok(warp::reply::with_status(
             warp::reply::json(&ApiResponse {
@@ -146,7 +150,7 @@ pub async fn handle_rejection(err: Rejection) -> Result<impl Reply, Rejection> {
             }),
             StatusCode::NOT_FOUND,
         ))
-    } else if let Some(_) = err.find::<ApiError>() {
+    } else if err.find::<ApiError>().is_some() {
         Ok(warp::reply::with_status(
             warp::reply::json(&ApiResponse {
                 success: false,
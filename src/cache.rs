@@ -0,0 +1,397 @@
+//! Caching of per-domain and per-address verification data.
+//!
+//! MX lookups, catch-all analyses, scraped email sets and SMTP verdicts are
+//! expensive to obtain and change rarely, so they are kept in a cache with a
+//! separate time-to-live per kind of entry. The storage is pluggable through
+//! [`CacheBackend`]: [`MemoryCache`] keeps entries for the lifetime of the
+//! process, [`SqliteCache`] persists them to a file so repeated runs over
+//! overlapping domains do not probe the same servers again.
+
+use crate::catch_all::CatchAllAnalysis;
+use crate::config::Config;
+use crate::dns::MailServer;
+use crate::error::{AppError, Result};
+use crate::formats::DomainFormat;
+use crate::models::{DomainInfo, SmtpVerificationResult, VerificationStatus};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const MX_NAMESPACE: &str = "mx";
+const DOMAIN_INFO_NAMESPACE: &str = "domain_info";
+const CATCH_ALL_NAMESPACE: &str = "catch_all";
+const SCRAPED_NAMESPACE: &str = "scraped_emails";
+const SMTP_NAMESPACE: &str = "smtp";
+const EMAIL_FORMAT_NAMESPACE: &str = "email_format";
+
+/// Storage for cache entries.
+///
+/// Values are opaque JSON strings grouped by namespace. Implementations must
+/// not return entries whose time-to-live has passed. The methods may block:
+/// they are called on Tokio's blocking thread pool, never on a worker thread.
+pub trait CacheBackend: Send + Sync + fmt::Debug {
+    /// Returns the value stored under `namespace`/`key`, if it has not expired.
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<String>>;
+
+    /// Stores `value` under `namespace`/`key`, replacing any previous entry.
+    fn put(&self, namespace: &str, key: &str, value: String, ttl: Duration) -> Result<()>;
+}
+
+/// A cache backend that keeps entries in memory.
+#[derive(Debug, Default)]
+pub struct MemoryCache {
+    entries: Mutex<HashMap<(String, String), (Instant, String)>>,
+}
+
+impl MemoryCache {
+    /// Creates an empty in-memory cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl CacheBackend for MemoryCache {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<String>> {
+        let mut entries = self.entries.lock().expect("memory cache lock poisoned");
+        let entry_key = (namespace.to_string(), key.to_string());
+        match entries.get(&entry_key) {
+            Some((expires_at, value)) if *expires_at > Instant::now() => Ok(Some(value.clone())),
+            Some(_) => {
+                entries.remove(&entry_key);
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn put(&self, namespace: &str, key: &str, value: String, ttl: Duration) -> Result<()> {
+        self.entries
+            .lock()
+            .expect("memory cache lock poisoned")
+            .insert(
+                (namespace.to_string(), key.to_string()),
+                (Instant::now() + ttl, value),
+            );
+        Ok(())
+    }
+}
+
+/// A cache backend that persists entries to an SQLite database file.
+#[derive(Debug)]
+pub struct SqliteCache {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteCache {
+    /// Opens (or creates) the cache database at `path` and removes expired
+    /// entries from it.
+    ///
+    /// # Arguments
+    /// * `path` - The database file. `":memory:"` opens a private in-memory database.
+    ///
+    /// # Returns
+    /// * `Ok(SqliteCache)` ready for use.
+    /// * `Err(AppError::Cache)` if the database cannot be opened or initialized.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let connection = Connection::open(path).map_err(|e| {
+            AppError::Cache(format!(
+                "Failed to open cache database {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        connection
+            .busy_timeout(Duration::from_secs(5))
+            .and_then(|_| {
+                connection.execute_batch(
+                    "CREATE TABLE IF NOT EXISTS cache_entries (
+                         namespace TEXT NOT NULL,
+                         key TEXT NOT NULL,
+                         value TEXT NOT NULL,
+                         expires_at INTEGER NOT NULL,
+                         PRIMARY KEY (namespace, key)
+                     );",
+                )
+            })
+            .and_then(|_| {
+                connection.execute(
+                    "DELETE FROM cache_entries WHERE expires_at <= ?1",
+                    params![unix_now()],
+                )
+            })
+            .map_err(|e| AppError::Cache(format!("Failed to initialize cache database: {}", e)))?;
+        tracing::info!("Using verification cache at {}", path.display());
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+}
+
+impl CacheBackend for SqliteCache {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<String>> {
+        self.connection
+            .lock()
+            .expect("sqlite cache lock poisoned")
+            .query_row(
+                "SELECT value FROM cache_entries
+                 WHERE namespace = ?1 AND key = ?2 AND expires_at > ?3",
+                params![namespace, key, unix_now()],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| AppError::Cache(format!("Cache lookup failed: {}", e)))
+    }
+
+    fn put(&self, namespace: &str, key: &str, value: String, ttl: Duration) -> Result<()> {
+        let expires_at =
+            unix_now().saturating_add(i64::try_from(ttl.as_secs()).unwrap_or(i64::MAX));
+        self.connection
+            .lock()
+            .expect("sqlite cache lock poisoned")
+            .execute(
+                "INSERT OR REPLACE INTO cache_entries (namespace, key, value, expires_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![namespace, key, value, expires_at],
+            )
+            .map(|_| ())
+            .map_err(|e| AppError::Cache(format!("Cache update failed: {}", e)))
+    }
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Typed access to a [`CacheBackend`] with the configured time-to-live per
+/// kind of entry. A zero TTL disables caching of that kind.
+///
+/// Cache failures are logged and treated as misses, so a broken cache never
+/// fails a verification.
+#[derive(Debug, Clone)]
+pub(crate) struct VerificationCache {
+    backend: Arc<dyn CacheBackend>,
+    mx_ttl: Duration,
+    catch_all_ttl: Duration,
+    scraped_emails_ttl: Duration,
+    smtp_ttl: Duration,
+    email_format_ttl: Duration,
+}
+
+impl VerificationCache {
+    /// Creates a cache over `backend` using the TTLs from `config`.
+    pub(crate) fn new(backend: Arc<dyn CacheBackend>, config: &Config) -> Self {
+        Self {
+            backend,
+            mx_ttl: config.mx_cache_ttl,
+            catch_all_ttl: config.catch_all_cache_ttl,
+            scraped_emails_ttl: config.scraped_emails_cache_ttl,
+            smtp_ttl: config.smtp_cache_ttl,
+            email_format_ttl: config.email_format_cache_ttl,
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        namespace: &'static str,
+        key: &str,
+        ttl: Duration,
+    ) -> Option<T> {
+        if ttl.is_zero() {
+            return None;
+        }
+        let backend = self.backend.clone();
+        let lookup_key = key.to_lowercase();
+        let lookup = tokio::task::spawn_blocking(move || backend.get(namespace, &lookup_key))
+            .await
+            .unwrap_or_else(|e| Err(AppError::Cache(format!("Cache lookup task failed: {}", e))));
+        let value = match lookup {
+            Ok(value) => value?,
+            Err(e) => {
+                tracing::warn!("Ignoring {} cache entry for {}: {}", namespace, key, e);
+                return None;
+            }
+        };
+        match serde_json::from_str(&value) {
+            Ok(parsed) => Some(parsed),
+            Err(e) => {
+                tracing::warn!(
+                    "Ignoring unreadable {} cache entry for {}: {}",
+                    namespace,
+                    key,
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    async fn put<T: Serialize + ?Sized>(
+        &self,
+        namespace: &'static str,
+        key: &str,
+        value: &T,
+        ttl: Duration,
+    ) {
+        if ttl.is_zero() {
+            return;
+        }
+        let stored = match serde_json::to_string(value) {
+            Ok(json) => {
+                let backend = self.backend.clone();
+                let store_key = key.to_lowercase();
+                tokio::task::spawn_blocking(move || backend.put(namespace, &store_key, json, ttl))
+                    .await
+                    .unwrap_or_else(|e| {
+                        Err(AppError::Cache(format!("Cache update task failed: {}", e)))
+                    })
+            }
+            Err(e) => Err(AppError::from(e)),
+        };
+        if let Err(e) = stored {
+            tracing::warn!("Failed to cache {} entry for {}: {}", namespace, key, e);
+        }
+    }
+
+    /// The cached mail servers of `domain`. An empty list records a null MX.
+    pub(crate) async fn mail_servers(&self, domain: &str) -> Option<Vec<MailServer>> {
+        self.get(MX_NAMESPACE, domain, self.mx_ttl).await
+    }
+
+    /// Caches the mail servers of `domain`.
+    pub(crate) async fn store_mail_servers(&self, domain: &str, mail_servers: &[MailServer]) {
+        self.put(MX_NAMESPACE, domain, mail_servers, self.mx_ttl)
+            .await;
+    }
+
+    /// The cached SPF, DMARC and MTA-STS data of `domain`. Shares the MX TTL.
+    pub(crate) async fn domain_info(&self, domain: &str) -> Option<DomainInfo> {
+        self.get(DOMAIN_INFO_NAMESPACE, domain, self.mx_ttl).await
+    }
+
+    /// Caches the SPF, DMARC and MTA-STS data of `domain`.
+    pub(crate) async fn store_domain_info(&self, domain: &str, info: &DomainInfo) {
+        self.put(DOMAIN_INFO_NAMESPACE, domain, info, self.mx_ttl)
+            .await;
+    }
+
+    /// The cached catch-all analysis of `domain`.
+    pub(crate) async fn catch_all(&self, domain: &str) -> Option<CatchAllAnalysis> {
+        self.get(CATCH_ALL_NAMESPACE, domain, self.catch_all_ttl)
+            .await
+    }
+
+    /// Caches the catch-all analysis of `domain`.
+    pub(crate) async fn store_catch_all(&self, domain: &str, analysis: &CatchAllAnalysis) {
+        self.put(CATCH_ALL_NAMESPACE, domain, analysis, self.catch_all_ttl)
+            .await;
+    }
+
+    /// The cached set of emails scraped from the website of `domain`.
+    pub(crate) async fn scraped_emails(&self, domain: &str) -> Option<Vec<String>> {
+        self.get(SCRAPED_NAMESPACE, domain, self.scraped_emails_ttl)
+            .await
+    }
+
+    /// Caches the emails scraped from the website of `domain`.
+    pub(crate) async fn store_scraped_emails(&self, domain: &str, emails: &[String]) {
+        self.put(SCRAPED_NAMESPACE, domain, emails, self.scraped_emails_ttl)
+            .await;
+    }
+
+    /// The address format evidence collected for `domain`.
+    pub(crate) async fn email_format(&self, domain: &str) -> Option<DomainFormat> {
+        self.get(EMAIL_FORMAT_NAMESPACE, domain, self.email_format_ttl)
+            .await
+    }
+
+    /// Caches the address format evidence collected for `domain`.
+    pub(crate) async fn store_email_format(&self, domain: &str, format: &DomainFormat) {
+        self.put(
+            EMAIL_FORMAT_NAMESPACE,
+            domain,
+            format,
+            self.email_format_ttl,
+        )
+        .await;
+    }
+
+    /// The cached SMTP verdict for `email`.
+    pub(crate) async fn smtp_result(&self, email: &str) -> Option<SmtpVerificationResult> {
+        self.get(SMTP_NAMESPACE, email, self.smtp_ttl).await
+    }
+
+    /// Caches the SMTP verdict for `email`. Only verdicts that a later probe
+    /// would most likely repeat are stored; transient outcomes such as
+    /// greylisting or timeouts are not.
+    pub(crate) async fn store_smtp_result(&self, email: &str, result: &SmtpVerificationResult) {
+        if matches!(
+            result.status,
+            VerificationStatus::Valid
+                | VerificationStatus::Invalid
+                | VerificationStatus::CatchAll
+                | VerificationStatus::MailboxFull
+                | VerificationStatus::Disabled
+        ) {
+            self.put(SMTP_NAMESPACE, email, result, self.smtp_ttl).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backends_expire_entries() {
+        let backends: Vec<Box<dyn CacheBackend>> = vec![
+            Box::new(MemoryCache::new()),
+            Box::new(SqliteCache::open(":memory:").unwrap()),
+        ];
+        for backend in backends {
+            backend
+                .put(
+                    "mx",
+                    "example.com",
+                    "[]".to_string(),
+                    Duration::from_secs(60),
+                )
+                .unwrap();
+            backend
+                .put("mx", "expired.com", "[]".to_string(), Duration::ZERO)
+                .unwrap();
+            assert_eq!(
+                backend.get("mx", "example.com").unwrap().as_deref(),
+                Some("[]")
+            );
+            assert_eq!(backend.get("smtp", "example.com").unwrap(), None);
+            assert_eq!(backend.get("mx", "expired.com").unwrap(), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_only_lasting_smtp_verdicts_are_cached() {
+        let cache = VerificationCache::new(Arc::new(MemoryCache::new()), &Config::default());
+
+        let valid = SmtpVerificationResult::conclusive(true, "250 OK".to_string());
+        cache.store_smtp_result("Jane@Example.com", &valid).await;
+        let cached = cache.smtp_result("jane@example.com").await.unwrap();
+        assert_eq!(cached.status, VerificationStatus::Valid);
+        assert_eq!(cached.message, "250 OK");
+
+        let greylisted = SmtpVerificationResult::inconclusive_retry("451 Try later".to_string())
+            .with_status(VerificationStatus::Greylisted);
+        cache
+            .store_smtp_result("john@example.com", &greylisted)
+            .await;
+        assert!(cache.smtp_result("john@example.com").await.is_none());
+    }
+}
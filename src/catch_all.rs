@@ -0,0 +1,174 @@
+//! Catch-all detection for mail domains.
+//!
+//! A catch-all domain accepts RCPT TO for any local part, so an accepted
+//! candidate proves nothing on its own. The domain is probed with several
+//! random local parts of different shapes and the replies are compared with
+//! the reply to the real candidate. Some servers accept only the first
+//! unknown recipient of a session, and some accept everything but word the
+//! reply differently for real mailboxes, so the outcome is a confidence
+//! rather than a yes/no answer.
+
+use crate::smtp_client::{EnhancedStatusCode, SmtpReply};
+use rand::Rng;
+use rand::distributions::Alphanumeric;
+use serde::{Deserialize, Serialize};
+
+/// Catch-all confidence from which accepted candidates are treated as unproven.
+const CATCH_ALL_THRESHOLD: f32 = 0.5;
+
+/// The parts of an RCPT reply that are compared between probes and candidates.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ReplyFingerprint {
+    code: u16,
+    enhanced: Option<EnhancedStatusCode>,
+    text: String,
+}
+
+impl ReplyFingerprint {
+    /// Builds the fingerprint of the reply to `RCPT TO:<recipient>`.
+    ///
+    /// The recipient address and digits (queue IDs, timestamps) are removed
+    /// from the text so that replies for different addresses compare equal
+    /// when the server answers them the same way.
+    pub(crate) fn new(reply: &SmtpReply, recipient: &str) -> Self {
+        let enhanced = reply.enhanced_code();
+        let recipient = recipient.to_lowercase();
+        let local_part = recipient.split('@').next().unwrap_or_default();
+        let mut text = reply.message().to_lowercase().replace(&recipient, "");
+        if !local_part.is_empty() {
+            text = text.replace(local_part, "");
+        }
+        let text = text
+            .split_whitespace()
+            .skip(usize::from(enhanced.is_some()))
+            .map(|word| word.replace(|c: char| c.is_ascii_digit(), ""))
+            .collect::<Vec<_>>()
+            .join(" ");
+        Self {
+            code: reply.code,
+            enhanced,
+            text,
+        }
+    }
+
+    fn is_accepted(&self) -> bool {
+        (200..300).contains(&self.code)
+    }
+}
+
+/// The outcome of probing a domain with random recipients.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct CatchAllAnalysis {
+    /// Share of random recipients the server accepted, from 0.0 to 1.0.
+    pub confidence: f32,
+    accepted_replies: Vec<ReplyFingerprint>,
+}
+
+impl CatchAllAnalysis {
+    /// Summarizes the replies to the random recipient probes.
+    pub(crate) fn from_probes(probes: Vec<ReplyFingerprint>) -> Self {
+        let total = probes.len();
+        let accepted_replies: Vec<ReplyFingerprint> =
+            probes.into_iter().filter(|p| p.is_accepted()).collect();
+        let confidence = if total == 0 {
+            0.0
+        } else {
+            accepted_replies.len() as f32 / total as f32
+        };
+        Self {
+            confidence,
+            accepted_replies,
+        }
+    }
+
+    /// True if the domain accepts enough random recipients to be considered a catch-all.
+    pub(crate) fn is_catch_all(&self) -> bool {
+        self.confidence >= CATCH_ALL_THRESHOLD
+    }
+
+    /// True if an accepted candidate cannot be told apart from the random
+    /// recipients, i.e. its reply matches one of the accepted probe replies.
+    pub(crate) fn is_indistinguishable(&self, candidate: &ReplyFingerprint) -> bool {
+        self.is_catch_all() && self.accepted_replies.iter().any(|p| p == candidate)
+    }
+}
+
+/// Generates local parts that are very unlikely to exist, cycling through
+/// several shapes so that pattern-based filters treat them like real names.
+pub(crate) fn random_local_parts(count: usize) -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    let mut letters = |len: usize| -> String {
+        (0..len)
+            .map(|_| char::from(rng.gen_range(b'a'..=b'z')))
+            .collect()
+    };
+    let mut local_parts = Vec::with_capacity(count);
+    for index in 0..count {
+        let local_part = match index % 4 {
+            0 => format!("{}.{}", letters(6), letters(9)),
+            1 => letters(12),
+            2 => format!("{}{}", letters(1), letters(8)),
+            _ => rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(14)
+                .map(|c| char::from(c).to_ascii_lowercase())
+                .collect(),
+        };
+        local_parts.push(local_part);
+    }
+    local_parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fingerprint(code: u16, text: &str, recipient: &str) -> ReplyFingerprint {
+        let reply = SmtpReply {
+            code,
+            lines: vec![text.to_string()],
+        };
+        ReplyFingerprint::new(&reply, recipient)
+    }
+
+    #[test]
+    fn test_catch_all_confidence() {
+        let all_accepted = CatchAllAnalysis::from_probes(vec![
+            fingerprint(250, "2.1.5 <abc@example.com> Ok", "abc@example.com"),
+            fingerprint(250, "2.1.5 <xyz@example.com> Ok", "xyz@example.com"),
+        ]);
+        assert_eq!(all_accepted.confidence, 1.0);
+        assert!(all_accepted.is_catch_all());
+
+        // Accepts the first unknown recipient of a session, rejects the rest.
+        let first_only = CatchAllAnalysis::from_probes(vec![
+            fingerprint(250, "2.1.5 Ok", "abc@example.com"),
+            fingerprint(550, "5.1.1 User unknown", "def@example.com"),
+            fingerprint(550, "5.1.1 User unknown", "ghi@example.com"),
+        ]);
+        assert!(first_only.confidence < 0.5);
+        assert!(!first_only.is_catch_all());
+    }
+
+    #[test]
+    fn test_candidate_distinguishable_by_reply_text() {
+        let analysis = CatchAllAnalysis::from_probes(vec![
+            fingerprint(250, "2.1.5 Accepted 1234", "abc@example.com"),
+            fingerprint(250, "2.1.5 Accepted 5678", "xyz@example.com"),
+        ]);
+
+        let same_reply = fingerprint(250, "2.1.5 Accepted 9012", "jane@example.com");
+        assert!(analysis.is_indistinguishable(&same_reply));
+
+        let different_reply = fingerprint(250, "2.1.5 Recipient ok", "jane@example.com");
+        assert!(!analysis.is_indistinguishable(&different_reply));
+    }
+
+    #[test]
+    fn test_random_local_parts_have_different_shapes() {
+        let local_parts = random_local_parts(4);
+        assert_eq!(local_parts.len(), 4);
+        assert!(local_parts[0].contains('.'));
+        assert!(local_parts.iter().all(|l| !l.is_empty()));
+    }
+}
@@ -1,102 +1,132 @@
 //! Defines the configuration settings for the email-sleuth application.
 
+use crate::error::AppError;
+use crate::names;
 use anyhow::Context;
-use clap::Parser;
-use once_cell::sync::Lazy;
+use clap::{Args, ValueEnum};
 use regex::Regex;
 use serde::Deserialize;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::net::IpAddr;
 use std::path::Path;
+use std::sync::Arc;
 use std::time::Duration;
 
-/// Command line arguments for email-sleuth
-#[derive(Parser, Debug)]
-#[command(author, version, about = "A Rust application to discover and verify professional email addresses", long_about = None)]
-pub(crate) struct AppArgs {
-    /// Path to the input JSON file containing contacts
-    #[arg(short, long, default_value = "input.json", env = "EMAIL_SLEUTH_INPUT")]
-    pub input: String,
-
-    /// Path to the output JSON file where results will be saved
-    #[arg(
-        short,
-        long,
-        default_value = "results.json",
-        env = "EMAIL_SLEUTH_OUTPUT"
-    )]
-    pub output: String,
-
+/// Command line options that override the configuration file.
+///
+/// These are flattened into the binary's top-level command so that every
+/// subcommand shares a single argument parser.
+#[derive(Args, Debug, Default)]
+pub struct AppArgs {
     /// Maximum number of concurrent tasks
-    #[arg(short, long, env = "EMAIL_SLEUTH_CONCURRENCY")]
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_CONCURRENCY")]
     pub concurrency: Option<usize>,
 
-    /// Name of the person to find email for (CLI mode)
-    #[arg(long, env = "EMAIL_SLEUTH_NAME")]
-    pub name: Option<String>,
-
-    /// Domain to search against (CLI mode)
-    #[arg(long, env = "EMAIL_SLEUTH_DOMAIN")]
-    pub domain: Option<String>,
-
-    /// Output results to stdout in CLI mode
-    #[arg(long, default_value = "false", env = "EMAIL_SLEUTH_STDOUT")]
-    pub stdout: bool,
-
     /// Path to configuration file (TOML format)
-    #[arg(long, env = "EMAIL_SLEUTH_CONFIG")]
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_CONFIG")]
     pub config_file: Option<String>,
 
     /// Maximum number of SMTP verification attempts
-    #[arg(long, env = "EMAIL_SLEUTH_MAX_VERIFICATION_ATTEMPTS")]
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_MAX_VERIFICATION_ATTEMPTS")]
     pub max_verification_attempts: Option<u32>,
 
     /// Minimum sleep between requests (seconds)
-    #[arg(long, env = "EMAIL_SLEUTH_MIN_SLEEP")]
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_MIN_SLEEP")]
     pub min_sleep: Option<f32>,
 
     /// Maximum sleep between requests (seconds)
-    #[arg(long, env = "EMAIL_SLEUTH_MAX_SLEEP")]
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_MAX_SLEEP")]
     pub max_sleep: Option<f32>,
 
     /// HTTP request timeout in seconds
-    #[arg(long, env = "EMAIL_SLEUTH_REQUEST_TIMEOUT")]
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_REQUEST_TIMEOUT")]
     pub request_timeout: Option<u64>,
 
     /// SMTP connection timeout in seconds
-    #[arg(long, env = "EMAIL_SLEUTH_SMTP_TIMEOUT")]
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_SMTP_TIMEOUT")]
     pub smtp_timeout: Option<u64>,
 
     /// DNS resolution timeout in seconds
-    #[arg(long, env = "EMAIL_SLEUTH_DNS_TIMEOUT")]
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_DNS_TIMEOUT")]
     pub dns_timeout: Option<u64>,
 
     /// Comma-separated list of DNS servers
-    #[arg(long, env = "EMAIL_SLEUTH_DNS_SERVERS")]
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_DNS_SERVERS")]
     pub dns_servers: Option<String>,
 
+    /// How DNS queries are sent: to the DNS servers, the system resolver, or
+    /// encrypted to the configured upstreams
+    #[arg(long, global = true, value_enum, env = "EMAIL_SLEUTH_DNS_MODE")]
+    pub dns_mode: Option<DnsMode>,
+
+    /// Zone file (TOML or hosts-style) answering DNS lookups before the DNS servers
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_DNS_ZONE_FILE")]
+    pub dns_zone_file: Option<String>,
+
     /// Comma-separated list of common pages to scrape
-    #[arg(long, env = "EMAIL_SLEUTH_COMMON_PAGES")]
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_COMMON_PAGES")]
     pub common_pages: Option<String>,
 
     /// User agent string for HTTP requests
-    #[arg(long, env = "EMAIL_SLEUTH_USER_AGENT")]
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_USER_AGENT")]
     pub user_agent: Option<String>,
 
     /// Sender email address for SMTP verification
-    #[arg(long, env = "EMAIL_SLEUTH_SMTP_SENDER")]
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_SMTP_SENDER")]
     pub smtp_sender: Option<String>,
 
+    /// Port to connect to on the mail servers
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_SMTP_PORT")]
+    pub smtp_port: Option<u16>,
+
+    /// Send every SMTP probe to this host instead of the domain's mail servers
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_SMTP_HOST")]
+    pub smtp_host: Option<String>,
+
+    /// STARTTLS policy for SMTP verification
+    #[arg(long, global = true, value_enum, env = "EMAIL_SLEUTH_SMTP_STARTTLS")]
+    pub smtp_starttls: Option<StartTlsMode>,
+
+    /// Hostname announced in the SMTP EHLO command
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_SMTP_EHLO_HOSTNAME")]
+    pub smtp_ehlo_hostname: Option<String>,
+
+    /// Number of random recipients sent to detect catch-all domains
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_CATCH_ALL_PROBES")]
+    pub catch_all_probes: Option<usize>,
+
+    /// Seconds to wait before re-verifying greylisted addresses (0 disables)
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_GREYLIST_RETRY_DELAY")]
+    pub greylist_retry_delay: Option<u64>,
+
+    /// Local IP address to bind outgoing SMTP connections to
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_SMTP_BIND_ADDRESS")]
+    pub smtp_bind_address: Option<IpAddr>,
+
+    /// Comma-separated list of source IP addresses to rotate through per domain
+    #[arg(
+        long,
+        global = true,
+        value_delimiter = ',',
+        env = "EMAIL_SLEUTH_SMTP_SOURCE_ADDRESSES"
+    )]
+    pub smtp_source_addresses: Option<Vec<IpAddr>>,
+
+    /// SQLite file to persist the verification cache in
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_CACHE_PATH")]
+    pub cache_path: Option<String>,
+
     /// Base confidence threshold score (0-10)
-    #[arg(long, env = "EMAIL_SLEUTH_CONFIDENCE_THRESHOLD")]
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_CONFIDENCE_THRESHOLD")]
     pub confidence_threshold: Option<u8>,
 
     /// Generic email confidence threshold score (0-10)
-    #[arg(long, env = "EMAIL_SLEUTH_GENERIC_CONFIDENCE_THRESHOLD")]
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_GENERIC_CONFIDENCE_THRESHOLD")]
     pub generic_confidence_threshold: Option<u8>,
 
     /// Maximum number of alternative emails to list
-    #[arg(long, env = "EMAIL_SLEUTH_MAX_ALTERNATIVES")]
+    #[arg(long, global = true, env = "EMAIL_SLEUTH_MAX_ALTERNATIVES")]
     pub max_alternatives: Option<usize>,
 }
 
@@ -108,9 +138,19 @@ struct ConfigFile {
     smtp: Option<SmtpConfig>,
     scraping: Option<ScrapingConfig>,
     verification: Option<VerificationConfig>,
+    cache: Option<CacheConfig>,
+    mail_providers: Option<Vec<MailProviderRule>>,
+    patterns: Option<PatternsConfig>,
     input_output: Option<InputOutputConfig>,
 }
 
+#[derive(Deserialize, Debug, Default)]
+struct PatternsConfig {
+    templates: Option<Vec<PatternTemplate>>,
+    nickname_file: Option<String>,
+    nickname_weight: Option<f32>,
+}
+
 #[derive(Deserialize, Debug, Default)]
 struct NetworkConfig {
     request_timeout: Option<u64>,
@@ -123,6 +163,11 @@ struct NetworkConfig {
 struct DnsConfig {
     dns_timeout: Option<u64>,
     dns_servers: Option<Vec<String>>,
+    mode: Option<DnsMode>,
+    upstreams: Option<Vec<DnsUpstream>>,
+    zone_file: Option<String>,
+    zone_fallback: Option<bool>,
+    domain_info: Option<bool>,
 }
 
 #[derive(Deserialize, Debug, Default)]
@@ -130,11 +175,22 @@ struct SmtpConfig {
     smtp_timeout: Option<u64>,
     smtp_sender_email: Option<String>,
     max_verification_attempts: Option<u32>,
+    port: Option<u16>,
+    host: Option<String>,
+    starttls: Option<StartTlsMode>,
+    greylist_retry_delay: Option<u64>,
+    catch_all_probes: Option<usize>,
+    ehlo_hostname: Option<String>,
+    bind_address: Option<IpAddr>,
+    source_addresses: Option<Vec<IpAddr>>,
 }
 
 #[derive(Deserialize, Debug, Default)]
 struct ScrapingConfig {
     common_pages: Option<Vec<String>>,
+    max_pages: Option<usize>,
+    max_depth: Option<usize>,
+    link_keywords: Option<Vec<String>>,
     generic_email_prefixes: Option<Vec<String>>,
 }
 
@@ -143,18 +199,127 @@ struct VerificationConfig {
     confidence_threshold: Option<u8>,
     generic_confidence_threshold: Option<u8>,
     max_alternatives: Option<usize>,
+    max_smtp_candidates: Option<usize>,
     max_concurrency: Option<usize>,
 }
 
+#[derive(Deserialize, Debug, Default)]
+struct CacheConfig {
+    path: Option<String>,
+    mx_ttl: Option<u64>,
+    catch_all_ttl: Option<u64>,
+    scraped_emails_ttl: Option<u64>,
+    email_format_ttl: Option<u64>,
+    smtp_ttl: Option<u64>,
+}
+
 #[derive(Deserialize, Debug, Default)]
 struct InputOutputConfig {
     input_file: Option<String>,
     output_file: Option<String>,
 }
 
+/// How DNS queries are sent.
+#[derive(Deserialize, ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DnsMode {
+    /// Plain UDP, with TCP fallback, to the configured DNS servers.
+    #[default]
+    Servers,
+    /// The system resolver configuration (`/etc/resolv.conf` on Unix).
+    System,
+    /// DNS-over-TLS to the configured upstreams.
+    Tls,
+    /// DNS-over-HTTPS to the configured upstreams.
+    Https,
+}
+
+/// An encrypted DNS upstream used by [`DnsMode::Tls`] and [`DnsMode::Https`].
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DnsUpstream {
+    /// IP address or host name of the upstream. Host names are resolved once
+    /// with the system resolver.
+    pub host: String,
+    /// Port to connect to. Defaults to 853 for DNS-over-TLS and 443 for
+    /// DNS-over-HTTPS.
+    pub port: Option<u16>,
+    /// Name the upstream's certificate must be valid for. Defaults to `host`;
+    /// required if `host` is an IP address.
+    pub tls_name: Option<String>,
+}
+
+/// Identifies a mail provider by the host names of a domain's mail exchangers.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MailProviderRule {
+    /// Name reported for matching domains, e.g. `"google_workspace"`.
+    pub name: String,
+    /// Domains whose hosts act as the provider's mail exchangers, e.g.
+    /// `"google.com"` matches `aspmx.l.google.com`.
+    pub mx_suffixes: Vec<String>,
+    /// Skip SMTP verification for domains hosted by this provider, e.g.
+    /// because its gateways accept every recipient.
+    #[serde(default)]
+    pub skip_smtp: bool,
+}
+
+impl MailProviderRule {
+    fn new(name: &str, mx_suffixes: &[&str], skip_smtp: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            mx_suffixes: mx_suffixes.iter().map(|s| s.to_string()).collect(),
+            skip_smtp,
+        }
+    }
+}
+
+/// A template for the local part of candidate addresses, such as
+/// `{first}.{last}` or `{f}{last}`.
+///
+/// Placeholders are `{first}`, `{middle}` and `{last}` for the name parts and
+/// `{f}`, `{m}` and `{l}` for their initials. `{first:3}` keeps only the
+/// first three characters of a part. Everything else is copied literally, so
+/// `{first}.{last}2` produces numbered variants. Templates using a part the
+/// contact does not have are skipped.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct PatternTemplate {
+    /// The local-part template.
+    pub template: String,
+    /// How common the format is, from 0.0 to 1.0. Higher weights give the
+    /// generated candidates a higher initial confidence.
+    pub weight: f32,
+}
+
+impl PatternTemplate {
+    fn new(template: &str, weight: f32) -> Self {
+        Self {
+            template: template.to_string(),
+            weight,
+        }
+    }
+}
+
+/// How the SMTP probe uses STARTTLS.
+#[derive(Deserialize, ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StartTlsMode {
+    /// Never upgrade the session, even if the server offers STARTTLS.
+    Disabled,
+    /// Upgrade when STARTTLS is advertised, without authenticating the
+    /// certificate. Falls back to plaintext if the handshake fails.
+    #[default]
+    Opportunistic,
+    /// Only probe over TLS with a certificate that is valid for the mail
+    /// server's hostname. Servers without STARTTLS are not probed.
+    Required,
+}
+
 /// Application configuration settings.
+///
+/// A `Config` can be built with [`Config::default`], loaded from a TOML file
+/// with [`load_config`], and then handed to an [`EmailSleuth`](crate::EmailSleuth)
+/// through its builder.
 #[derive(Debug, Clone)]
-pub(crate) struct Config {
+pub struct Config {
     /// Path to the input JSON file containing contacts.
     pub input_file: String,
     /// Path to the output JSON file where results will be saved.
@@ -173,6 +338,12 @@ pub(crate) struct Config {
     pub dns_timeout: Duration,
     /// Common sub-pages to check for contact information during scraping.
     pub common_pages_to_scrape: Vec<String>,
+    /// Most pages fetched from one website, including the start page.
+    pub max_pages_to_scrape: usize,
+    /// How many links away from the start page the crawler follows.
+    pub max_crawl_depth: usize,
+    /// Words in a link's path or text that make the crawler follow it.
+    pub crawl_link_keywords: Vec<String>,
     /// Regex pattern for matching email addresses.
     pub email_regex: Regex,
     /// Set of common generic email prefixes (e.g., "info", "contact").
@@ -181,25 +352,82 @@ pub(crate) struct Config {
     pub user_agent: String,
     /// Sender email address to use in the SMTP MAIL FROM command.
     pub smtp_sender_email: String,
-    /// DNS servers to use for resolution.
+    /// Port to connect to on the mail servers.
+    pub smtp_port: u16,
+    /// Host that receives every SMTP probe instead of the resolved mail
+    /// servers, e.g. a relay or a local test server. MX lookups are skipped
+    /// when set.
+    pub smtp_host: Option<String>,
+    /// STARTTLS policy for SMTP verification sessions.
+    pub smtp_starttls: StartTlsMode,
+    /// Number of random recipients sent to each domain to detect catch-all
+    /// behavior.
+    pub catch_all_probes: usize,
+    /// How long to wait before re-verifying greylisted addresses. Zero
+    /// disables deferred re-verification. Only records processed with
+    /// [`EmailSleuth::process_record_deferring`](crate::EmailSleuth::process_record_deferring)
+    /// (as the command-line batch mode does) are re-verified.
+    pub greylist_retry_delay: Duration,
+    /// Hostname announced in the SMTP EHLO command. Should match the reverse
+    /// DNS of the address probes are sent from.
+    pub smtp_ehlo_hostname: String,
+    /// Local address to bind outgoing SMTP connections to.
+    pub smtp_bind_address: Option<IpAddr>,
+    /// Source addresses to spread SMTP probes over, one per domain. Takes
+    /// precedence over `smtp_bind_address` when not empty.
+    pub smtp_source_addresses: Vec<IpAddr>,
+    /// SQLite file the verification cache is persisted to. The cache is kept
+    /// in memory only if unset.
+    pub cache_path: Option<String>,
+    /// How long resolved mail servers are cached. Zero disables caching.
+    pub mx_cache_ttl: Duration,
+    /// How long catch-all analyses are cached. Zero disables caching.
+    pub catch_all_cache_ttl: Duration,
+    /// How long the emails scraped from a website are cached. Zero disables
+    /// caching.
+    pub scraped_emails_cache_ttl: Duration,
+    /// How long the address format learned for a domain is kept.
+    pub email_format_cache_ttl: Duration,
+    /// How long conclusive SMTP verdicts are cached. Zero disables caching.
+    pub smtp_cache_ttl: Duration,
+    /// DNS servers to use for resolution in [`DnsMode::Servers`].
     pub dns_servers: Vec<String>,
+    /// How DNS queries are sent.
+    pub dns_mode: DnsMode,
+    /// Upstreams for DNS-over-TLS and DNS-over-HTTPS.
+    pub dns_upstreams: Vec<DnsUpstream>,
+    /// Zone file (TOML or hosts-style) whose records are used instead of
+    /// querying the DNS servers.
+    pub dns_zone_file: Option<String>,
+    /// Whether names missing from `dns_zone_file` are resolved with the DNS
+    /// servers. Disable for fully offline runs.
+    pub dns_zone_fallback: bool,
+    /// Whether the SPF, DMARC and MTA-STS records of each domain are looked up
+    /// and reported.
+    pub fetch_domain_info: bool,
     /// Confidence score threshold to select an email as "most likely".
     pub confidence_threshold: u8,
     /// Confidence score above which a generic email might be selected as "most likely".
     pub generic_confidence_threshold: u8,
     /// Maximum number of alternative emails to list in the output.
     pub max_alternatives: usize,
-    /// Flag indicating if the application is running in CLI mode (processing a single contact).
-    pub cli_mode: bool,
-    /// The name provided via command line when running in CLI mode.
-    pub cli_name: Option<String>,
-    /// The domain provided via command line when running in CLI mode.
-    pub cli_domain: Option<String>,
-    /// Flag to output results to stdout instead of a file in CLI mode.
-    pub output_to_stdout: bool,
+    /// Most candidates per contact verified over SMTP, taken in order of
+    /// confidence.
+    pub max_smtp_candidates: usize,
+    /// Rules identifying mail providers from MX records, tried in order.
+    pub mail_providers: Vec<MailProviderRule>,
+    /// Templates candidate addresses are generated from.
+    pub pattern_templates: Vec<PatternTemplate>,
+    /// Nicknames by canonical first name (e.g. `robert` → `bob`, `rob`),
+    /// used to generate extra candidates.
+    pub nicknames: Arc<HashMap<String, Vec<String>>>,
+    /// Factor applied to the prior weight of candidates generated from a
+    /// nickname instead of the given first name. The default keeps them below
+    /// the confidence needed for SMTP verification.
+    pub nickname_weight: f32,
 }
 
-impl Config {
+impl Default for Config {
     fn default() -> Self {
         let common_pages = vec![
             "/contact",
@@ -282,18 +510,84 @@ impl Config {
             smtp_timeout: Duration::from_secs(5),
             dns_timeout: Duration::from_secs(5),
             common_pages_to_scrape: common_pages.iter().map(|s| s.to_string()).collect(),
+            max_pages_to_scrape: 25,
+            max_crawl_depth: 2,
+            crawl_link_keywords: [
+                "team",
+                "about",
+                "contact",
+                "leadership",
+                "people",
+                "imprint",
+                "impressum",
+                "staff",
+                "management",
+                "kontakt",
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
             email_regex,
             generic_email_prefixes: generic_prefixes,
             user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/118.0.0.0 Safari/537.36".to_string(),
             smtp_sender_email: "verify-probe@example.com".to_string(),
+            smtp_port: 25,
+            smtp_host: None,
+            smtp_starttls: StartTlsMode::default(),
+            greylist_retry_delay: Duration::from_secs(300),
+            catch_all_probes: 3,
+            smtp_ehlo_hostname: "localhost".to_string(),
+            smtp_bind_address: None,
+            smtp_source_addresses: Vec::new(),
+            cache_path: None,
+            mx_cache_ttl: Duration::from_secs(24 * 60 * 60),
+            catch_all_cache_ttl: Duration::from_secs(7 * 24 * 60 * 60),
+            scraped_emails_cache_ttl: Duration::from_secs(24 * 60 * 60),
+            email_format_cache_ttl: Duration::from_secs(30 * 24 * 60 * 60),
+            smtp_cache_ttl: Duration::from_secs(7 * 24 * 60 * 60),
             dns_servers,
+            dns_mode: DnsMode::default(),
+            dns_upstreams: Vec::new(),
+            dns_zone_file: None,
+            dns_zone_fallback: true,
+            fetch_domain_info: true,
             confidence_threshold: 4,
             generic_confidence_threshold: 7,
             max_alternatives: 5,
-            cli_mode: false,
-            cli_name: None,
-            cli_domain: None,
-            output_to_stdout: false,
+            max_smtp_candidates: 10,
+            mail_providers: vec![
+                MailProviderRule::new("google_workspace", &["google.com", "googlemail.com"], false),
+                MailProviderRule::new("microsoft_365", &["protection.outlook.com"], false),
+                MailProviderRule::new("zoho", &["zoho.com", "zoho.eu", "zoho.in"], false),
+                MailProviderRule::new("proofpoint", &["pphosted.com", "ppe-hosted.com"], true),
+                MailProviderRule::new("mimecast", &["mimecast.com", "mimecast.co.za"], true),
+                MailProviderRule::new("barracuda", &["barracudanetworks.com"], false),
+                MailProviderRule::new("yahoo", &["yahoodns.net"], false),
+                MailProviderRule::new("fastmail", &["messagingengine.com"], false),
+            ],
+            pattern_templates: vec![
+                PatternTemplate::new("{first}.{last}", 0.8),
+                PatternTemplate::new("{f}{last}", 0.6),
+                PatternTemplate::new("{first}", 0.6),
+                PatternTemplate::new("{first}{last}", 0.5),
+                PatternTemplate::new("{f}.{last}", 0.5),
+                PatternTemplate::new("{last}.{first}", 0.4),
+                PatternTemplate::new("{last}{first}", 0.4),
+                PatternTemplate::new("{first}{l}", 0.4),
+                PatternTemplate::new("{first}.{l}", 0.4),
+                PatternTemplate::new("{first}_{last}", 0.4),
+                PatternTemplate::new("{first}-{last}", 0.4),
+                PatternTemplate::new("{first}.{m}.{last}", 0.4),
+                PatternTemplate::new("{f}{m}{last}", 0.4),
+                PatternTemplate::new("{last}_{first}", 0.3),
+                PatternTemplate::new("{last}-{first}", 0.3),
+                PatternTemplate::new("{first}{m}{last}", 0.3),
+                PatternTemplate::new("{first:3}{last}", 0.2),
+                PatternTemplate::new("{first}{last:3}", 0.2),
+                PatternTemplate::new("{first}.{middle}.{last}", 0.2),
+            ],
+            nicknames: Arc::new(names::bundled_nicknames()),
+            nickname_weight: 0.25,
         }
     }
 }
@@ -316,7 +610,7 @@ fn load_config_file(file_path: &str) -> anyhow::Result<ConfigFile> {
     Ok(config)
 }
 
-fn apply_file_config(config: &mut Config, file_config: &ConfigFile) {
+fn apply_file_config(config: &mut Config, file_config: &ConfigFile) -> anyhow::Result<()> {
     if let Some(network) = &file_config.network {
         if let Some(timeout) = network.request_timeout {
             config.request_timeout = Duration::from_secs(timeout);
@@ -339,6 +633,21 @@ fn apply_file_config(config: &mut Config, file_config: &ConfigFile) {
         if let Some(servers) = &dns.dns_servers {
             config.dns_servers = servers.clone();
         }
+        if let Some(mode) = dns.mode {
+            config.dns_mode = mode;
+        }
+        if let Some(upstreams) = &dns.upstreams {
+            config.dns_upstreams = upstreams.clone();
+        }
+        if let Some(zone_file) = &dns.zone_file {
+            config.dns_zone_file = Some(zone_file.clone());
+        }
+        if let Some(fallback) = dns.zone_fallback {
+            config.dns_zone_fallback = fallback;
+        }
+        if let Some(domain_info) = dns.domain_info {
+            config.fetch_domain_info = domain_info;
+        }
     }
 
     if let Some(smtp) = &file_config.smtp {
@@ -348,6 +657,30 @@ fn apply_file_config(config: &mut Config, file_config: &ConfigFile) {
         if let Some(sender) = &smtp.smtp_sender_email {
             config.smtp_sender_email = sender.clone();
         }
+        if let Some(port) = smtp.port {
+            config.smtp_port = port;
+        }
+        if let Some(host) = &smtp.host {
+            config.smtp_host = Some(host.clone());
+        }
+        if let Some(mode) = smtp.starttls {
+            config.smtp_starttls = mode;
+        }
+        if let Some(delay) = smtp.greylist_retry_delay {
+            config.greylist_retry_delay = Duration::from_secs(delay);
+        }
+        if let Some(probes) = smtp.catch_all_probes {
+            config.catch_all_probes = probes;
+        }
+        if let Some(hostname) = &smtp.ehlo_hostname {
+            config.smtp_ehlo_hostname = hostname.clone();
+        }
+        if let Some(address) = smtp.bind_address {
+            config.smtp_bind_address = Some(address);
+        }
+        if let Some(addresses) = &smtp.source_addresses {
+            config.smtp_source_addresses = addresses.clone();
+        }
         if let Some(attempts) = smtp.max_verification_attempts {
             config.max_verification_attempts = attempts;
         }
@@ -357,8 +690,17 @@ fn apply_file_config(config: &mut Config, file_config: &ConfigFile) {
         if let Some(pages) = &scraping.common_pages {
             config.common_pages_to_scrape = pages.clone();
         }
+        if let Some(max_pages) = scraping.max_pages {
+            config.max_pages_to_scrape = max_pages;
+        }
+        if let Some(max_depth) = scraping.max_depth {
+            config.max_crawl_depth = max_depth;
+        }
+        if let Some(keywords) = &scraping.link_keywords {
+            config.crawl_link_keywords = keywords.iter().map(|k| k.to_lowercase()).collect();
+        }
         if let Some(prefixes) = &scraping.generic_email_prefixes {
-            config.generic_email_prefixes = prefixes.iter().map(|s| s.clone()).collect();
+            config.generic_email_prefixes = prefixes.iter().cloned().collect();
         }
     }
 
@@ -372,11 +714,57 @@ fn apply_file_config(config: &mut Config, file_config: &ConfigFile) {
         if let Some(max_alt) = verification.max_alternatives {
             config.max_alternatives = max_alt;
         }
+        if let Some(max_smtp) = verification.max_smtp_candidates {
+            config.max_smtp_candidates = max_smtp;
+        }
         if let Some(concurrency) = verification.max_concurrency {
             config.max_concurrency = concurrency;
         }
     }
 
+    if let Some(cache) = &file_config.cache {
+        if let Some(path) = &cache.path {
+            config.cache_path = Some(path.clone());
+        }
+        if let Some(ttl) = cache.mx_ttl {
+            config.mx_cache_ttl = Duration::from_secs(ttl);
+        }
+        if let Some(ttl) = cache.catch_all_ttl {
+            config.catch_all_cache_ttl = Duration::from_secs(ttl);
+        }
+        if let Some(ttl) = cache.scraped_emails_ttl {
+            config.scraped_emails_cache_ttl = Duration::from_secs(ttl);
+        }
+        if let Some(ttl) = cache.email_format_ttl {
+            config.email_format_cache_ttl = Duration::from_secs(ttl);
+        }
+        if let Some(ttl) = cache.smtp_ttl {
+            config.smtp_cache_ttl = Duration::from_secs(ttl);
+        }
+    }
+
+    if let Some(rules) = &file_config.mail_providers {
+        config.mail_providers = rules.clone();
+    }
+
+    if let Some(patterns) = &file_config.patterns {
+        if let Some(templates) = &patterns.templates {
+            config.pattern_templates = templates.clone();
+        }
+        if let Some(path) = &patterns.nickname_file {
+            let extra = fs::read_to_string(path)
+                .map_err(AppError::from)
+                .and_then(|text| names::parse_nicknames(&text))
+                .map_err(|e| anyhow::anyhow!("Invalid nickname file {}: {}", path, e))?;
+            let mut nicknames = names::bundled_nicknames();
+            names::merge_nicknames(&mut nicknames, extra);
+            config.nicknames = Arc::new(nicknames);
+        }
+        if let Some(weight) = patterns.nickname_weight {
+            config.nickname_weight = weight;
+        }
+    }
+
     if let Some(io_config) = &file_config.input_output {
         if let Some(input) = &io_config.input_file {
             config.input_file = input.clone();
@@ -385,18 +773,11 @@ fn apply_file_config(config: &mut Config, file_config: &ConfigFile) {
             config.output_file = output.clone();
         }
     }
+    Ok(())
 }
 
 /// Apply command line arguments to the Config instance
 fn apply_cli_args(config: &mut Config, args: &AppArgs) {
-    config.input_file = args.input.clone();
-    config.output_file = args.output.clone();
-
-    config.cli_name = args.name.clone();
-    config.cli_domain = args.domain.clone();
-    config.cli_mode = args.name.is_some() && args.domain.is_some();
-    config.output_to_stdout = args.stdout;
-
     if let Some(concurrency) = args.concurrency {
         config.max_concurrency = concurrency;
     }
@@ -433,6 +814,14 @@ fn apply_cli_args(config: &mut Config, args: &AppArgs) {
             .collect();
     }
 
+    if let Some(mode) = args.dns_mode {
+        config.dns_mode = mode;
+    }
+
+    if let Some(ref zone_file) = args.dns_zone_file {
+        config.dns_zone_file = Some(zone_file.clone());
+    }
+
     if let Some(ref pages) = args.common_pages {
         config.common_pages_to_scrape = pages
             .split(',')
@@ -449,6 +838,42 @@ fn apply_cli_args(config: &mut Config, args: &AppArgs) {
         config.smtp_sender_email = sender.clone();
     }
 
+    if let Some(port) = args.smtp_port {
+        config.smtp_port = port;
+    }
+
+    if let Some(ref host) = args.smtp_host {
+        config.smtp_host = Some(host.clone());
+    }
+
+    if let Some(mode) = args.smtp_starttls {
+        config.smtp_starttls = mode;
+    }
+
+    if let Some(delay) = args.greylist_retry_delay {
+        config.greylist_retry_delay = Duration::from_secs(delay);
+    }
+
+    if let Some(probes) = args.catch_all_probes {
+        config.catch_all_probes = probes;
+    }
+
+    if let Some(ref hostname) = args.smtp_ehlo_hostname {
+        config.smtp_ehlo_hostname = hostname.clone();
+    }
+
+    if let Some(address) = args.smtp_bind_address {
+        config.smtp_bind_address = Some(address);
+    }
+
+    if let Some(ref addresses) = args.smtp_source_addresses {
+        config.smtp_source_addresses = addresses.clone();
+    }
+
+    if let Some(ref path) = args.cache_path {
+        config.cache_path = Some(path.clone());
+    }
+
     if let Some(threshold) = args.confidence_threshold {
         config.confidence_threshold = threshold;
     }
@@ -476,6 +901,51 @@ fn validate_config(config: &mut Config) -> anyhow::Result<()> {
         tracing::warn!("DNS servers list was empty. Setting to default public DNS servers.");
     }
 
+    if matches!(config.dns_mode, DnsMode::Tls | DnsMode::Https) && config.dns_upstreams.is_empty() {
+        anyhow::bail!(
+            "DNS mode {:?} requires at least one entry in [dns] upstreams",
+            config.dns_mode
+        );
+    }
+
+    for template in &config.pattern_templates {
+        crate::patterns::validate_template(&template.template)
+            .map_err(|e| anyhow::anyhow!("Invalid pattern template: {}", e))?;
+        if !(0.0..=1.0).contains(&template.weight) {
+            anyhow::bail!(
+                "Pattern template '{}' has weight {}; weights must be between 0.0 and 1.0",
+                template.template,
+                template.weight
+            );
+        }
+    }
+
+    if !(0.0..=1.0).contains(&config.nickname_weight) {
+        anyhow::bail!(
+            "Nickname weight {} must be between 0.0 and 1.0",
+            config.nickname_weight
+        );
+    }
+
+    if config.max_smtp_candidates == 0 {
+        anyhow::bail!("max_smtp_candidates must be at least 1");
+    }
+
+    if config.max_pages_to_scrape == 0 {
+        config.max_pages_to_scrape = 1;
+        tracing::warn!("Max pages to scrape was set to 0. Setting to 1.");
+    }
+
+    if config.catch_all_probes == 0 {
+        config.catch_all_probes = 1;
+        tracing::warn!("Catch-all probes was set to 0. Setting to 1.");
+    }
+
+    if config.smtp_ehlo_hostname.trim().is_empty() {
+        config.smtp_ehlo_hostname = "localhost".to_string();
+        tracing::warn!("SMTP EHLO hostname was empty. Setting to 'localhost'.");
+    }
+
     if config.confidence_threshold > 10 {
         config.confidence_threshold = 10;
         tracing::warn!("Confidence threshold exceeded maximum (10). Setting to 10.");
@@ -502,14 +972,15 @@ fn validate_config(config: &mut Config) -> anyhow::Result<()> {
     Ok(())
 }
 
-pub(crate) fn build_config() -> anyhow::Result<Config> {
-    let args = AppArgs::parse();
-
-    let mut config = Config::default();
-
-    if let Some(ref file_path) = args.config_file {
+/// Applies the explicitly given configuration file, or the first default
+/// location that exists, on top of `config`.
+///
+/// # Returns
+/// * `Err` if a loaded file refers to a nickname file that cannot be used.
+fn apply_config_files(config: &mut Config, file_path: Option<&str>) -> anyhow::Result<()> {
+    if let Some(file_path) = file_path {
         match load_config_file(file_path) {
-            Ok(file_config) => apply_file_config(&mut config, &file_config),
+            Ok(file_config) => apply_file_config(config, &file_config)?,
             Err(e) => {
                 tracing::error!("Failed to load configuration file: {}", e);
             }
@@ -525,7 +996,7 @@ pub(crate) fn build_config() -> anyhow::Result<Config> {
             if Path::new(path).exists() {
                 match load_config_file(path) {
                     Ok(file_config) => {
-                        apply_file_config(&mut config, &file_config);
+                        apply_file_config(config, &file_config)?;
                         break;
                     }
                     Err(e) => {
@@ -535,8 +1006,33 @@ pub(crate) fn build_config() -> anyhow::Result<Config> {
             }
         }
     }
+    Ok(())
+}
+
+/// Loads the configuration from the default file locations without looking
+/// at command line arguments.
+///
+/// # Arguments
+/// * `file_path` - An explicit TOML file to load instead of the default locations.
+///
+/// # Returns
+/// * `Ok(Config)` with defaults overridden by the file settings.
+/// * `Err` if the resulting configuration fails validation.
+pub fn load_config(file_path: Option<&str>) -> anyhow::Result<Config> {
+    let mut config = Config::default();
+    apply_config_files(&mut config, file_path)?;
+    validate_config(&mut config)?;
+    Ok(config)
+}
+
+/// Builds the configuration from defaults, the configuration file and the
+/// already-parsed command line arguments, in increasing order of precedence.
+pub fn build_config(args: &AppArgs) -> anyhow::Result<Config> {
+    let mut config = Config::default();
 
-    apply_cli_args(&mut config, &args);
+    apply_config_files(&mut config, args.config_file.as_deref())?;
+
+    apply_cli_args(&mut config, args);
 
     validate_config(&mut config)?;
 
@@ -545,24 +1041,35 @@ pub(crate) fn build_config() -> anyhow::Result<Config> {
     Ok(config)
 }
 
-pub(crate) fn parse_args() -> AppArgs {
-    AppArgs::parse()
-}
-
-pub(crate) static CONFIG: Lazy<Config> = Lazy::new(|| match build_config() {
-    Ok(config) => config,
-    Err(e) => {
-        eprintln!("ERROR: Failed to build configuration: {}", e);
-        Config::default()
-    }
-});
-
-pub(crate) fn get_random_sleep_duration() -> Duration {
+/// Picks a random delay within the configured sleep range.
+pub(crate) fn get_random_sleep_duration(config: &Config) -> Duration {
     use rand::Rng;
-    let (min, max) = CONFIG.sleep_between_requests;
+    let (min, max) = config.sleep_between_requests;
     if min >= max {
         return Duration::from_secs_f32(min);
     }
     let duration_secs = rand::thread_rng().gen_range(min..max);
     Duration::from_secs_f32(duration_secs)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_config_rejects_unusable_nickname_file() {
+        let path = std::env::temp_dir().join(format!(
+            "email-sleuth-nickname-test-{}.toml",
+            std::process::id()
+        ));
+        fs::write(
+            &path,
+            "[patterns]\nnickname_file = \"/nonexistent/nicknames.txt\"\n",
+        )
+        .unwrap();
+        let result = load_config(path.to_str());
+        fs::remove_file(&path).unwrap();
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("nickname file"), "{}", error);
+    }
+}
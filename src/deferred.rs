@@ -0,0 +1,106 @@
+//! Deferred re-verification of greylisted addresses.
+//!
+//! Greylisting servers reject the first delivery attempt from an unknown
+//! client with a 4xx reply and only accept a retry after several minutes.
+//! Instead of stalling a contact until then, greylisted addresses are handed
+//! to a [`DeferredVerifications`] queue that re-probes them in the background
+//! while the rest of the batch is processed.
+
+use crate::cache::VerificationCache;
+use crate::config::Config;
+use crate::dns::MailServer;
+use crate::models::SmtpVerificationResult;
+use crate::resolver::MailResolver;
+use crate::smtp::verify_emails_smtp_with_retries;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+
+type DeferredJob = JoinHandle<HashMap<String, SmtpVerificationResult>>;
+
+/// A queue of greylisted addresses waiting to be verified again.
+///
+/// Create one per batch, pass it to
+/// [`EmailSleuth::process_record_deferring`](crate::EmailSleuth::process_record_deferring)
+/// for every record, then hand it to
+/// [`EmailSleuth::complete_deferred`](crate::EmailSleuth::complete_deferred)
+/// together with the batch results once all records are processed.
+///
+/// Clones share the same queue, so it can be moved into spawned tasks.
+#[derive(Debug, Clone, Default)]
+pub struct DeferredVerifications {
+    jobs: Arc<Mutex<Vec<DeferredJob>>>,
+}
+
+impl DeferredVerifications {
+    /// Creates an empty queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of scheduled re-verification jobs.
+    pub fn len(&self) -> usize {
+        self.jobs
+            .lock()
+            .expect("deferred queue lock poisoned")
+            .len()
+    }
+
+    /// True if no re-verification has been scheduled.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Schedules a re-verification of `emails` once the configured greylisting
+    /// delay has passed. The probe runs in a background task.
+    pub(crate) fn schedule(
+        &self,
+        emails: Vec<String>,
+        mail_servers: Vec<MailServer>,
+        resolver: Arc<dyn MailResolver>,
+        config: Arc<Config>,
+        cache: Arc<VerificationCache>,
+    ) {
+        tracing::info!(target: "smtp_task",
+            "Deferring verification of {} greylisted candidates for {:?}",
+            emails.len(),
+            config.greylist_retry_delay
+        );
+        let job = tokio::spawn(async move {
+            tokio::time::sleep(config.greylist_retry_delay).await;
+            verify_emails_smtp_with_retries(
+                &emails,
+                &mail_servers,
+                resolver.as_ref(),
+                &config,
+                &cache,
+            )
+            .await
+        });
+        self.jobs
+            .lock()
+            .expect("deferred queue lock poisoned")
+            .push(job);
+    }
+
+    /// Waits for every scheduled job and returns the combined results.
+    pub(crate) async fn wait_all(self) -> HashMap<String, SmtpVerificationResult> {
+        let jobs: Vec<DeferredJob> = self
+            .jobs
+            .lock()
+            .expect("deferred queue lock poisoned")
+            .drain(..)
+            .collect();
+
+        let mut results = HashMap::new();
+        for job in jobs {
+            match job.await {
+                Ok(job_results) => results.extend(job_results),
+                Err(e) => {
+                    tracing::error!(target: "smtp_task", "Deferred verification task failed: {}", e);
+                }
+            }
+        }
+        results
+    }
+}
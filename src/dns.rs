@@ -1,14 +1,24 @@
-//! Functions for performing DNS lookups (MX, A records).
+//! Functions for performing DNS lookups (MX, A records) and reading the
+//! mail-related records of a domain (SPF, DMARC, MTA-STS).
 
-use crate::config::CONFIG;
+use crate::config::{Config, DnsMode, MailProviderRule};
 use crate::error::{AppError, Result};
+use crate::models::{DmarcRecord, DomainInfo, MtaStsInfo, SpfRecord};
+use crate::resolver::{MailResolver, MxRecord, StaticResolver, TrustDnsResolver};
+use rand::seq::SliceRandom;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
 use std::net::{IpAddr, SocketAddr};
 use std::str::FromStr;
+use std::sync::Arc;
 use trust_dns_resolver::TokioAsyncResolver;
-use trust_dns_resolver::config::{LookupIpStrategy, ResolverConfig, ResolverOpts};
+use trust_dns_resolver::config::{
+    LookupIpStrategy, NameServerConfig, Protocol, ResolverConfig, ResolverOpts,
+};
+use trust_dns_resolver::system_conf::read_system_conf;
 
 /// Represents the result of a mail server lookup.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct MailServer {
     /// The domain name or IP address of the mail server.
     pub exchange: String,
@@ -17,29 +27,89 @@ pub(crate) struct MailServer {
     pub preference: u16,
 }
 
-/// Creates a configured DNS resolver instance.
-pub(crate) async fn create_resolver() -> Result<TokioAsyncResolver> {
-    let mut resolver_config = ResolverConfig::new();
+/// The mail provider a domain's mail exchangers belong to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MailProvider {
+    /// The provider name from the matching rule, or `"self_hosted"`.
+    pub name: String,
+    /// Whether SMTP verification should be skipped for this provider.
+    pub skip_smtp: bool,
+}
+
+/// Creates the resolver described by `config`: trust-dns in the configured
+/// DNS mode, answered first from the static zone file if one is set.
+pub(crate) async fn create_resolver(config: &Config) -> Result<Arc<dyn MailResolver>> {
+    let network_resolver: Arc<dyn MailResolver> = Arc::new(create_network_resolver(config).await?);
+
+    match &config.dns_zone_file {
+        Some(zone_file) => {
+            let static_resolver = StaticResolver::from_file(zone_file)?;
+            if config.dns_zone_fallback {
+                Ok(Arc::new(static_resolver.with_fallback(network_resolver)))
+            } else {
+                Ok(Arc::new(static_resolver))
+            }
+        }
+        None => Ok(network_resolver),
+    }
+}
+
+/// Creates a trust-dns resolver for the configured DNS mode and timeout.
+async fn create_network_resolver(config: &Config) -> Result<TrustDnsResolver> {
+    let (resolver_config, mut resolver_opts) = match config.dns_mode {
+        DnsMode::System => read_system_conf().map_err(|e| {
+            AppError::Config(format!(
+                "Failed to read the system DNS configuration: {}",
+                e
+            ))
+        })?,
+        DnsMode::Servers => {
+            let mut resolver_config = ResolverConfig::new();
+            for name_server in plain_name_servers(&config.dns_servers)? {
+                resolver_config.add_name_server(name_server);
+            }
+            (resolver_config, ResolverOpts::default())
+        }
+        DnsMode::Tls | DnsMode::Https => {
+            let mut resolver_config = ResolverConfig::new();
+            for name_server in encrypted_name_servers(config).await? {
+                resolver_config.add_name_server(name_server);
+            }
+            (resolver_config, ResolverOpts::default())
+        }
+    };
+
+    resolver_opts.timeout = config.dns_timeout;
+    resolver_opts.attempts = 2;
+    resolver_opts.ip_strategy = LookupIpStrategy::Ipv4AndIpv6;
+
+    let resolver = TokioAsyncResolver::tokio(resolver_config, resolver_opts);
+    tracing::debug!(
+        "DNS resolver configured in {:?} mode with timeout {:?}.",
+        config.dns_mode,
+        config.dns_timeout
+    );
+    Ok(TrustDnsResolver::new(resolver))
+}
 
-    for server_str in &CONFIG.dns_servers {
+/// Builds UDP name servers with TCP fallback on port 53 for the given IP addresses.
+fn plain_name_servers(dns_servers: &[String]) -> Result<Vec<NameServerConfig>> {
+    let mut name_servers = Vec::new();
+    for server_str in dns_servers {
         match IpAddr::from_str(server_str) {
             Ok(ip_addr) => {
                 // Default DNS port is 53
                 let socket_addr = SocketAddr::new(ip_addr, 53);
-                resolver_config.add_name_server(trust_dns_resolver::config::NameServerConfig {
-                    socket_addr,
-                    protocol: trust_dns_resolver::config::Protocol::Udp, // Start with UDP
-                    tls_dns_name: None,
-                    trust_negative_responses: true,
-                    bind_addr: None,
-                });
-                resolver_config.add_name_server(trust_dns_resolver::config::NameServerConfig {
-                    socket_addr,
-                    protocol: trust_dns_resolver::config::Protocol::Tcp, // Also allow TCP fallback
-                    tls_dns_name: None,
-                    trust_negative_responses: true,
-                    bind_addr: None,
-                });
+                for protocol in [Protocol::Udp, Protocol::Tcp] {
+                    name_servers.push(NameServerConfig {
+                        socket_addr,
+                        protocol,
+                        tls_dns_name: None,
+                        trust_negative_responses: true,
+                        tls_config: None,
+                        bind_addr: None,
+                    });
+                }
             }
             Err(e) => {
                 tracing::error!(
@@ -54,161 +124,608 @@ pub(crate) async fn create_resolver() -> Result<TokioAsyncResolver> {
             }
         }
     }
+    Ok(name_servers)
+}
 
-    let mut resolver_opts = ResolverOpts::default();
-    resolver_opts.timeout = CONFIG.dns_timeout;
-    resolver_opts.attempts = 2;
-    resolver_opts.ip_strategy = LookupIpStrategy::Ipv4AndIpv6;
+/// Builds DNS-over-TLS or DNS-over-HTTPS name servers for the configured
+/// upstreams. Upstream host names are resolved once with the system resolver.
+async fn encrypted_name_servers(config: &Config) -> Result<Vec<NameServerConfig>> {
+    let (protocol, default_port) = match config.dns_mode {
+        DnsMode::Https => (Protocol::Https, 443),
+        _ => (Protocol::Tls, 853),
+    };
 
-    let resolver = TokioAsyncResolver::tokio(resolver_config, resolver_opts);
-    tracing::debug!("DNS resolver configured with public servers and timeout.");
-    Ok(resolver)
+    let mut name_servers = Vec::new();
+    for upstream in &config.dns_upstreams {
+        let port = upstream.port.unwrap_or(default_port);
+        let tls_name = match (&upstream.tls_name, upstream.host.parse::<IpAddr>()) {
+            (Some(tls_name), _) => tls_name.clone(),
+            (None, Err(_)) => upstream.host.clone(),
+            (None, Ok(_)) => {
+                return Err(AppError::Config(format!(
+                    "DNS upstream {} needs a tls_name to verify its certificate",
+                    upstream.host
+                )));
+            }
+        };
+        let addresses: Vec<SocketAddr> = match upstream.host.parse::<IpAddr>() {
+            Ok(ip_addr) => vec![SocketAddr::new(ip_addr, port)],
+            Err(_) => tokio::net::lookup_host((upstream.host.as_str(), port))
+                .await?
+                .collect(),
+        };
+        for socket_addr in addresses {
+            name_servers.push(NameServerConfig {
+                socket_addr,
+                protocol,
+                tls_dns_name: Some(tls_name.clone()),
+                trust_negative_responses: true,
+                tls_config: None,
+                bind_addr: None,
+            });
+        }
+    }
+
+    if name_servers.is_empty() {
+        return Err(AppError::Config(format!(
+            "DNS mode {:?} needs at least one upstream in [dns] upstreams",
+            config.dns_mode
+        )));
+    }
+    Ok(name_servers)
 }
 
-/// Resolves the mail server(s) for a given domain, checking MX records first,
-/// then falling back to A records.
+/// Orders MX records for delivery attempts as described in RFC 5321 section 5.1:
+/// ascending by preference, with hosts of equal preference shuffled so load is
+/// spread across them.
+///
+/// Records with an empty exchange are dropped.
+fn order_mail_exchangers(records: Vec<MxRecord>) -> Vec<MailServer> {
+    let mut servers: Vec<MailServer> = records
+        .into_iter()
+        .map(|record| MailServer {
+            exchange: record.exchange.trim_end_matches('.').to_string(),
+            preference: record.preference,
+        })
+        .filter(|server| !server.exchange.is_empty())
+        .collect();
+
+    // Shuffle first, then stable-sort by preference: equal-preference hosts end
+    // up in random relative order.
+    servers.shuffle(&mut rand::thread_rng());
+    servers.sort_by_key(|server| server.preference);
+    servers
+}
+
+/// True if `host` is `suffix` or a subdomain of it.
+fn host_in_domain(host: &str, suffix: &str) -> bool {
+    let host = host.trim_end_matches('.').to_lowercase();
+    let suffix = suffix
+        .trim_start_matches('.')
+        .trim_end_matches('.')
+        .to_lowercase();
+    host == suffix || host.ends_with(&format!(".{}", suffix))
+}
+
+/// Identifies who hosts the mail of `domain` from its mail exchangers.
+///
+/// The exchangers are checked in preference order against `rules`; the first
+/// rule matching an exchanger wins. If no rule matches and the primary
+/// exchanger lives within `domain` itself (or is an address from the A record
+/// fallback), the domain is considered self-hosted.
+///
+/// # Returns
+/// * `Some(MailProvider)` if the provider could be identified.
+/// * `None` if the exchangers belong to an unknown third party.
+pub(crate) fn classify_mail_provider(
+    mail_servers: &[MailServer],
+    domain: &str,
+    rules: &[MailProviderRule],
+) -> Option<MailProvider> {
+    for server in mail_servers {
+        if let Some(rule) = rules.iter().find(|rule| {
+            rule.mx_suffixes
+                .iter()
+                .any(|suffix| host_in_domain(&server.exchange, suffix))
+        }) {
+            return Some(MailProvider {
+                name: rule.name.clone(),
+                skip_smtp: rule.skip_smtp,
+            });
+        }
+    }
+
+    let primary = mail_servers.first()?;
+    if host_in_domain(&primary.exchange, domain) || primary.exchange.parse::<IpAddr>().is_ok() {
+        return Some(MailProvider {
+            name: "self_hosted".to_string(),
+            skip_smtp: false,
+        });
+    }
+    None
+}
+
+/// Logs a failed lookup according to its cause.
+fn log_lookup_error(error: &AppError, domain: &str, lookup: &str) {
+    match error {
+        AppError::NxDomain(_) => {
+            tracing::error!(
+                "Domain {} does not exist (NXDOMAIN) during {}",
+                domain,
+                lookup
+            )
+        }
+        AppError::DnsTimeout(_) => tracing::error!("DNS timeout during {} for {}", lookup, domain),
+        e => tracing::error!(
+            "Unexpected DNS error during {} for {}: {}",
+            lookup,
+            domain,
+            e
+        ),
+    }
+}
+
+/// Resolves the mail servers for a given domain, checking MX records first,
+/// then falling back to A/AAAA records if the domain has no MX records.
 ///
 /// # Arguments
-/// * `resolver` - A configured `TokioAsyncResolver` instance.
+/// * `resolver` - The resolver to query.
 /// * `domain` - The domain name to resolve.
 ///
 /// # Returns
-/// * `Ok(MailServer)` containing the most preferred mail server found.
+/// * `Ok(Vec<MailServer>)` containing every mail exchanger in the order delivery
+///   should be attempted. Never empty.
+/// * `Err(AppError::NullMx)` if the domain publishes a null MX, i.e. accepts no
+///   email. No fallback is attempted.
 /// * `Err(AppError)` if resolution fails (e.g., NXDOMAIN, NoAnswer, Timeout).
-pub(crate) async fn resolve_mail_server(
-    resolver: &TokioAsyncResolver,
+pub(crate) async fn resolve_mail_servers(
+    resolver: &dyn MailResolver,
     domain: &str,
-) -> Result<MailServer> {
+) -> Result<Vec<MailServer>> {
     tracing::debug!("Performing DNS MX lookup for {}", domain);
 
-    match resolver.mx_lookup(domain).await {
-        Ok(mx_response) => {
-            let mut mx_records: Vec<_> = mx_response.iter().collect();
-            if mx_records.is_empty() {
-                tracing::warn!(
-                    "No MX records returned by resolver for {}, though lookup succeeded.",
-                    domain
-                );
-                return resolve_a_record_fallback(resolver, domain).await;
-            }
+    let mx_records = resolver.lookup_mx(domain).await.inspect_err(|e| {
+        log_lookup_error(e, domain, "MX lookup");
+    })?;
+    if mx_records.is_empty() {
+        tracing::warn!(
+            "No MX records found (NoAnswer) for {}. Trying A record fallback...",
+            domain
+        );
+        return resolve_a_record_fallback(resolver, domain).await;
+    }
+    if is_null_mx(&mx_records) {
+        tracing::info!("{} publishes a null MX: it accepts no email", domain);
+        return Err(AppError::NullMx(domain.to_string()));
+    }
 
-            mx_records.sort_by_key(|r| r.preference());
-
-            if let Some(best_mx) = mx_records.first() {
-                let exchange = best_mx
-                    .exchange()
-                    .to_utf8()
-                    .trim_end_matches('.')
-                    .to_string();
-                let preference = best_mx.preference();
-                if exchange.is_empty() {
-                    tracing::error!(
-                        "Empty mail server name found in highest priority MX record for {}",
-                        domain
-                    );
-                    return Err(AppError::NoDnsRecords(format!(
-                        "Empty exchange in MX record for {}",
-                        domain
-                    )));
-                }
-                tracing::info!(
-                    "Found MX for {}: {} (Pref: {})",
-                    domain,
-                    exchange,
-                    preference
-                );
-                Ok(MailServer {
-                    exchange,
-                    preference,
-                })
-            } else {
-                tracing::warn!(
-                    "MX lookup for {} succeeded but yielded no processable records.",
-                    domain
-                );
-                resolve_a_record_fallback(resolver, domain).await
+    let mail_servers = order_mail_exchangers(mx_records);
+    if mail_servers.is_empty() {
+        tracing::error!(
+            "Only empty mail server names found in MX records for {}",
+            domain
+        );
+        return Err(AppError::NoDnsRecords(format!(
+            "Empty exchange in MX record for {}",
+            domain
+        )));
+    }
+
+    tracing::info!(
+        "Found {} MX for {}: {}",
+        mail_servers.len(),
+        domain,
+        mail_servers
+            .iter()
+            .map(|ms| format!("{} (Pref: {})", ms.exchange, ms.preference))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    Ok(mail_servers)
+}
+
+/// Uses the A/AAAA records of the domain as its mail servers (RFC 5321
+/// implicit MX), trying every address in the order returned.
+async fn resolve_a_record_fallback(
+    resolver: &dyn MailResolver,
+    domain: &str,
+) -> Result<Vec<MailServer>> {
+    tracing::debug!("Attempting A record fallback for {}", domain);
+    let addresses = resolver.lookup_ip(domain).await.inspect_err(|e| {
+        log_lookup_error(e, domain, "A record fallback");
+    })?;
+    if addresses.is_empty() {
+        tracing::error!("No MX or A records found for {}", domain);
+        return Err(AppError::NoDnsRecords(domain.to_string()));
+    }
+    let mail_servers: Vec<MailServer> = addresses
+        .iter()
+        .map(|ip_addr| MailServer {
+            exchange: ip_addr.to_string(),
+            preference: u16::MAX,
+        })
+        .collect();
+    tracing::info!(
+        "Using address records for {} as mail servers: {}",
+        domain,
+        mail_servers
+            .iter()
+            .map(|ms| ms.exchange.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    Ok(mail_servers)
+}
+
+/// Looks up the mail-related records of `domain`: its SPF and DMARC
+/// policies and its MTA-STS policy (fetching the policy file over HTTPS if
+/// the `_mta-sts` record exists).
+///
+/// Failed lookups are logged and leave the corresponding field empty, so this
+/// never fails.
+///
+/// # Arguments
+/// * `resolver` - The resolver to query.
+/// * `http_client` - The client used to fetch the MTA-STS policy file.
+/// * `domain` - The domain to inspect.
+/// * `null_mx` - Whether the domain's MX lookup, already done by the caller
+///   with [`resolve_mail_servers`], found a null MX.
+pub(crate) async fn fetch_domain_info(
+    resolver: &dyn MailResolver,
+    http_client: &Client,
+    domain: &str,
+    null_mx: bool,
+) -> DomainInfo {
+    tracing::debug!("Looking up SPF, DMARC and MTA-STS records for {}", domain);
+    let dmarc_name = format!("_dmarc.{}", domain);
+    let mta_sts_name = format!("_mta-sts.{}", domain);
+    let (spf, dmarc, mta_sts) = tokio::join!(
+        find_txt_record(resolver, domain, "v=spf1"),
+        find_txt_record(resolver, &dmarc_name, "v=DMARC1"),
+        find_txt_record(resolver, &mta_sts_name, "v=STSv1"),
+    );
+
+    let mta_sts = match mta_sts {
+        Some(record) => {
+            let mut info = MtaStsInfo {
+                id: record_tags(&record)
+                    .find(|(tag, _)| tag == "id")
+                    .map(|(_, value)| value.to_string()),
+                ..MtaStsInfo::default()
+            };
+            if let Some(policy) = fetch_mta_sts_policy(http_client, domain).await {
+                let parsed = parse_mta_sts_policy(&policy);
+                info.mode = parsed.mode;
+                info.mx = parsed.mx;
+                info.max_age = parsed.max_age;
             }
+            Some(info)
         }
+        None => None,
+    };
+
+    DomainInfo {
+        spf: spf.as_deref().map(parse_spf),
+        dmarc: dmarc.as_deref().map(parse_dmarc),
+        mta_sts,
+        null_mx,
+    }
+}
+
+/// True if `records` is a null MX (RFC 7505): only exchanges named ".".
+pub(crate) fn is_null_mx(records: &[MxRecord]) -> bool {
+    !records.is_empty()
+        && records
+            .iter()
+            .all(|record| record.exchange.trim_end_matches('.').is_empty())
+}
+
+/// Returns the first TXT record of `name` starting with `version`, such as
+/// `v=spf1`. Lookup failures are treated as no record.
+async fn find_txt_record(resolver: &dyn MailResolver, name: &str, version: &str) -> Option<String> {
+    let records = match resolver.lookup_txt(name).await {
+        Ok(records) => records,
         Err(e) => {
-            let error_string = format!("{:?}", e.kind());
+            tracing::debug!("TXT lookup for {} failed: {}", name, e);
+            return None;
+        }
+    };
+    records.into_iter().find(|record| {
+        let record = record.trim_start();
+        record
+            .get(..version.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(version))
+            && record[version.len()..]
+                .chars()
+                .next()
+                .is_none_or(|c| c == ' ' || c == ';')
+    })
+}
 
-            if error_string.contains("NoRecordsFound") {
-                tracing::warn!(
-                    "No MX records found (NoAnswer) for {}. Trying A record fallback...",
-                    domain
-                );
-                resolve_a_record_fallback(resolver, domain).await
-            } else if error_string.contains("NXDomain")
-                || error_string.contains("Name does not exist")
-            {
-                tracing::error!("Domain {} does not exist (NXDOMAIN)", domain);
-                Err(AppError::NxDomain(domain.to_string()))
-            } else if error_string.contains("Timeout") {
-                tracing::error!("DNS resolution timeout for {}", domain);
-                Err(AppError::DnsTimeout(domain.to_string()))
+/// Splits a `tag=value; tag=value` record into lowercase tags and trimmed values.
+fn record_tags(record: &str) -> impl Iterator<Item = (String, &str)> {
+    record.split(';').filter_map(|part| {
+        part.split_once('=')
+            .map(|(tag, value)| (tag.trim().to_lowercase(), value.trim()))
+    })
+}
+
+/// Parses an SPF record such as `v=spf1 include:_spf.google.com ~all`.
+pub(crate) fn parse_spf(record: &str) -> SpfRecord {
+    let mut spf = SpfRecord {
+        record: record.to_string(),
+        ..SpfRecord::default()
+    };
+    for term in record.split_whitespace().skip(1) {
+        let lower = term.to_lowercase();
+        let mechanism = lower.trim_start_matches(['+', '-', '~', '?']);
+        if mechanism == "all" {
+            spf.all = Some(if lower.len() > mechanism.len() {
+                lower.clone()
             } else {
-                tracing::error!("Unexpected DNS resolution error for {}: {}", domain, e);
-                Err(AppError::Dns(e))
-            }
+                "+all".to_string()
+            });
+        } else if let Some(included) = mechanism.strip_prefix("include:") {
+            spf.includes.push(included.to_string());
+        } else {
+            spf.mechanisms.push(lower.clone());
         }
     }
+    spf
 }
 
-/// Attempts to resolve an A record for the domain as a fallback mail server.
-async fn resolve_a_record_fallback(
-    resolver: &TokioAsyncResolver,
-    domain: &str,
-) -> Result<MailServer> {
-    tracing::debug!("Attempting A record fallback for {}", domain);
-    match resolver.lookup_ip(domain).await {
-        Ok(a_response) => {
-            if let Some(ip_addr) = a_response.iter().next() {
-                let mail_server_ip = ip_addr.to_string();
-                tracing::info!(
-                    "Using A record for {} as mail server: {}",
-                    domain,
-                    mail_server_ip
-                );
-                Ok(MailServer {
-                    exchange: mail_server_ip,
-                    preference: u16::MAX,
-                })
-            } else {
-                tracing::error!("No MX or A records found for {}", domain);
-                Err(AppError::NoDnsRecords(domain.to_string()))
+/// Parses a DMARC record such as `v=DMARC1; p=reject; rua=mailto:d@example.com`.
+pub(crate) fn parse_dmarc(record: &str) -> DmarcRecord {
+    let mut dmarc = DmarcRecord {
+        record: record.to_string(),
+        ..DmarcRecord::default()
+    };
+    for (tag, value) in record_tags(record) {
+        match tag.as_str() {
+            "p" => dmarc.policy = Some(value.to_lowercase()),
+            "sp" => dmarc.subdomain_policy = Some(value.to_lowercase()),
+            "pct" => dmarc.percentage = value.parse().ok(),
+            "rua" => {
+                dmarc.aggregate_reports = value
+                    .split(',')
+                    .map(|uri| uri.trim().to_string())
+                    .filter(|uri| !uri.is_empty())
+                    .collect()
             }
+            _ => {}
+        }
+    }
+    dmarc
+}
+
+/// Fetches `https://mta-sts.<domain>/.well-known/mta-sts.txt`.
+async fn fetch_mta_sts_policy(http_client: &Client, domain: &str) -> Option<String> {
+    let url = format!("https://mta-sts.{}/.well-known/mta-sts.txt", domain);
+    let response = match http_client.get(&url).send().await {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => {
+            tracing::debug!("MTA-STS policy {} returned {}", url, response.status());
+            return None;
         }
         Err(e) => {
-            let error_string = format!("{:?}", e.kind());
+            tracing::debug!("Failed to fetch MTA-STS policy {}: {}", url, e);
+            return None;
+        }
+    };
+    response
+        .text()
+        .await
+        .inspect_err(|e| tracing::debug!("Failed to read MTA-STS policy {}: {}", url, e))
+        .ok()
+}
 
-            if error_string.contains("NoRecordsFound") {
-                tracing::error!(
-                    "No MX records found, and no A records found either for {}",
-                    domain
-                );
-                Err(AppError::NoDnsRecords(domain.to_string()))
-            } else if error_string.contains("NXDomain")
-                || error_string.contains("Name does not exist")
-            {
-                tracing::error!(
-                    "Domain {} does not exist (NXDOMAIN) during A record fallback",
-                    domain
-                );
-                Err(AppError::NxDomain(domain.to_string()))
-            } else if error_string.contains("Timeout") {
-                tracing::error!("DNS timeout during A record fallback for {}", domain);
-                Err(AppError::DnsTimeout(format!(
-                    "A record fallback for {}",
-                    domain
-                )))
-            } else {
-                tracing::error!(
-                    "A record fallback failed for {} after NoAnswer MX: {}",
-                    domain,
-                    e
-                );
-                Err(AppError::Dns(e))
-            }
+/// Parses an MTA-STS policy file (RFC 8461): `key: value` lines.
+pub(crate) fn parse_mta_sts_policy(policy: &str) -> MtaStsInfo {
+    let mut info = MtaStsInfo::default();
+    for line in policy.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim().to_lowercase().as_str() {
+            "mode" => info.mode = Some(value.to_lowercase()),
+            "mx" => info.mx.push(value.to_lowercase()),
+            "max_age" => info.max_age = value.parse().ok(),
+            _ => {}
         }
     }
+    info
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DnsUpstream;
+
+    fn mx(preference: u16, exchange: &str) -> MxRecord {
+        MxRecord {
+            preference,
+            exchange: exchange.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_order_mail_exchangers_by_preference() {
+        let servers = order_mail_exchangers(vec![
+            mx(20, "backup.example.com."),
+            mx(10, "mx1.example.com."),
+            mx(30, "."),
+        ]);
+        let exchanges: Vec<&str> = servers.iter().map(|s| s.exchange.as_str()).collect();
+        assert_eq!(exchanges, vec!["mx1.example.com", "backup.example.com"]);
+    }
+
+    #[test]
+    fn test_order_mail_exchangers_keeps_equal_preference_group() {
+        let servers = order_mail_exchangers(vec![
+            mx(10, "b.example.com"),
+            mx(5, "primary.example.com"),
+            mx(10, "a.example.com"),
+            mx(10, "c.example.com"),
+        ]);
+        assert_eq!(servers.len(), 4);
+        assert_eq!(servers[0].exchange, "primary.example.com");
+        let mut rest: Vec<&str> = servers[1..].iter().map(|s| s.exchange.as_str()).collect();
+        rest.sort();
+        assert_eq!(
+            rest,
+            vec!["a.example.com", "b.example.com", "c.example.com"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_mail_servers_from_static_zone() {
+        let resolver = StaticResolver::from_hosts(
+            "example.com  MX  10 mx1.example.com.\n\
+             web.example  A   192.0.2.80\n\
+             web.example  A   192.0.2.81\n\
+             web.example  AAAA 2001:db8::80\n\
+             web.example  TXT \"no mail here\"\n\
+             parked.example  MX  0 .\n\
+             parked.example  A   192.0.2.90\n",
+        )
+        .unwrap();
+
+        let servers = resolve_mail_servers(&resolver, "example.com")
+            .await
+            .unwrap();
+        assert_eq!(servers[0].exchange, "mx1.example.com");
+
+        let fallback = resolve_mail_servers(&resolver, "web.example")
+            .await
+            .unwrap();
+        let exchanges: Vec<&str> = fallback.iter().map(|s| s.exchange.as_str()).collect();
+        assert_eq!(exchanges, vec!["192.0.2.80", "192.0.2.81", "2001:db8::80"]);
+        assert!(fallback.iter().all(|s| s.preference == u16::MAX));
+
+        // A null MX is final: the address records are not used.
+        assert!(matches!(
+            resolve_mail_servers(&resolver, "parked.example").await,
+            Err(AppError::NullMx(_))
+        ));
+
+        assert!(matches!(
+            resolve_mail_servers(&resolver, "missing.example").await,
+            Err(AppError::NxDomain(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_name_servers() {
+        let mut config = Config {
+            dns_mode: DnsMode::Tls,
+            dns_upstreams: vec![DnsUpstream {
+                host: "1.1.1.1".to_string(),
+                port: None,
+                tls_name: Some("cloudflare-dns.com".to_string()),
+            }],
+            ..Config::default()
+        };
+        let name_servers = encrypted_name_servers(&config).await.unwrap();
+        assert_eq!(name_servers.len(), 1);
+        assert_eq!(name_servers[0].protocol, Protocol::Tls);
+        assert_eq!(name_servers[0].socket_addr.port(), 853);
+        assert_eq!(
+            name_servers[0].tls_dns_name.as_deref(),
+            Some("cloudflare-dns.com")
+        );
+
+        config.dns_mode = DnsMode::Https;
+        let name_servers = encrypted_name_servers(&config).await.unwrap();
+        assert_eq!(name_servers[0].protocol, Protocol::Https);
+        assert_eq!(name_servers[0].socket_addr.port(), 443);
+
+        // An IP address alone cannot be used to verify the certificate.
+        config.dns_upstreams[0].tls_name = None;
+        assert!(encrypted_name_servers(&config).await.is_err());
+    }
+
+    #[test]
+    fn test_classify_mail_provider() {
+        let rules = Config::default().mail_providers;
+        let servers = |exchanges: &[&str]| -> Vec<MailServer> {
+            exchanges
+                .iter()
+                .enumerate()
+                .map(|(index, exchange)| MailServer {
+                    exchange: exchange.to_string(),
+                    preference: index as u16 * 10,
+                })
+                .collect()
+        };
+        let provider =
+            |exchanges: &[&str]| classify_mail_provider(&servers(exchanges), "example.com", &rules);
+
+        let google = provider(&["aspmx.l.google.com", "alt1.aspmx.l.google.com"]).unwrap();
+        assert_eq!(google.name, "google_workspace");
+        assert!(!google.skip_smtp);
+
+        let microsoft = provider(&["example-com.mail.protection.outlook.com"]).unwrap();
+        assert_eq!(microsoft.name, "microsoft_365");
+
+        let proofpoint = provider(&["mx0a-001.pphosted.com"]).unwrap();
+        assert_eq!(proofpoint.name, "proofpoint");
+        assert!(proofpoint.skip_smtp);
+
+        assert_eq!(provider(&["mail.example.com"]).unwrap().name, "self_hosted");
+        assert_eq!(provider(&["192.0.2.25"]).unwrap().name, "self_hosted");
+        assert_eq!(provider(&["mx.hosting-company.net"]), None);
+        // Suffixes match whole labels only.
+        assert_eq!(provider(&["mx.notgoogle.com"]), None);
+    }
+
+    #[test]
+    fn test_parse_domain_records() {
+        let spf =
+            parse_spf("v=spf1 include:_spf.google.com include:sendgrid.net ip4:192.0.2.0/24 ~all");
+        assert_eq!(spf.includes, vec!["_spf.google.com", "sendgrid.net"]);
+        assert_eq!(spf.mechanisms, vec!["ip4:192.0.2.0/24"]);
+        assert_eq!(spf.all.as_deref(), Some("~all"));
+        assert!(!spf.authorizes_no_senders());
+        assert!(parse_spf("v=spf1 -all").authorizes_no_senders());
+
+        let dmarc = parse_dmarc(
+            "v=DMARC1; p=Reject; sp=none; pct=50; rua=mailto:a@example.com, mailto:b@example.com",
+        );
+        assert_eq!(dmarc.policy.as_deref(), Some("reject"));
+        assert_eq!(dmarc.subdomain_policy.as_deref(), Some("none"));
+        assert_eq!(dmarc.percentage, Some(50));
+        assert_eq!(dmarc.aggregate_reports.len(), 2);
+
+        let policy = parse_mta_sts_policy(
+            "version: STSv1\r\nmode: enforce\r\nmx: mail.example.com\r\nmx: *.example.net\r\nmax_age: 604800\r\n",
+        );
+        assert_eq!(policy.mode.as_deref(), Some("enforce"));
+        assert_eq!(policy.mx, vec!["mail.example.com", "*.example.net"]);
+        assert_eq!(policy.max_age, Some(604800));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_domain_info_from_static_zone() {
+        let resolver = StaticResolver::from_hosts(
+            "example.com         MX  10 mx1.example.com.\n\
+             example.com         TXT \"google-site-verification=abc\"\n\
+             example.com         TXT \"v=spf1 include:_spf.google.com ~all\"\n\
+             _dmarc.example.com  TXT \"v=DMARC1; p=quarantine\"\n\
+             parked.example      MX  0 .\n\
+             parked.example      TXT \"v=spf1 -all\"\n",
+        )
+        .unwrap();
+        let client = Client::new();
+
+        let info = fetch_domain_info(&resolver, &client, "example.com", false).await;
+        assert_eq!(info.spf.unwrap().includes, vec!["_spf.google.com"]);
+        assert_eq!(info.dmarc.unwrap().policy.as_deref(), Some("quarantine"));
+        assert_eq!(info.mta_sts, None);
+        assert!(!info.null_mx);
+
+        let parked = fetch_domain_info(&resolver, &client, "parked.example", true).await;
+        assert!(parked.null_mx);
+        assert!(parked.declares_no_mail());
+    }
 }
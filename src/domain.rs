@@ -74,7 +74,7 @@ pub(crate) fn normalize_url(website_url_str: &str) -> Result<Url> {
         } else {
             website_url_str.to_string()
         };
-    Url::parse(&url_str_with_scheme).map_err(|e| AppError::UrlParse(e))
+    Url::parse(&url_str_with_scheme).map_err(AppError::UrlParse)
 }
 
 #[cfg(test)]
@@ -6,7 +6,7 @@ use url::ParseError as UrlParseError;
 
 /// The primary error type for the email finding process.
 #[derive(Error, Debug)]
-pub(crate) enum AppError {
+pub enum AppError {
     /// Error occurring during configuration loading or validation.
     #[error("Configuration Error: {0}")]
     Config(String),
@@ -43,23 +43,31 @@ pub(crate) enum AppError {
     #[error("No DNS Records Found (MX/A): {0}")]
     NoDnsRecords(String),
 
+    /// The domain publishes a null MX record (RFC 7505): it accepts no email.
+    #[error("Domain Accepts No Email (null MX): {0}")]
+    NullMx(String),
+
     /// DNS operation timed out.
     #[error("DNS Timeout for domain: {0}")]
     DnsTimeout(String),
 
-    /// Error during SMTP communication setup or command execution.
+    /// Error during SMTP communication (e.g., malformed reply, connection closed).
     #[error("SMTP Error: {0}")]
-    Smtp(#[from] lettre::transport::smtp::Error),
+    Smtp(String),
 
     /// Error related to SMTP command execution details.
     #[error("SMTP Command Error: Code={code}, Message='{message}'")]
     SmtpCommand {
         /// The SMTP status code.
-        code: lettre::transport::smtp::response::Code,
+        code: u16,
         /// The message returned by the server.
         message: String,
     },
 
+    /// An SMTP connection attempt or command did not complete in time.
+    #[error("SMTP Timeout: {0}")]
+    SmtpTimeout(String),
+
     /// Error specifically during the STARTTLS handshake.
     #[error("SMTP STARTTLS Error: {0}")]
     SmtpTls(String),
@@ -68,6 +76,10 @@ pub(crate) enum AppError {
     #[error("Address Parsing Error: {0}")]
     AddrParse(#[from] AddrParseError),
 
+    /// Error reading from or writing to the verification cache.
+    #[error("Cache Error: {0}")]
+    Cache(String),
+
     /// Error related to concurrency or task execution.
     #[error("Task Execution Error: {0}")]
     Task(String),
@@ -97,4 +109,4 @@ pub(crate) enum AppError {
     SmtpInconclusive(String),
 }
 
-pub(crate) type Result<T> = std::result::Result<T, AppError>;
+pub type Result<T> = std::result::Result<T, AppError>;
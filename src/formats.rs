@@ -0,0 +1,326 @@
+//! Learning the address format a company uses from addresses already known at
+//! its domain.
+//!
+//! An address scraped from the company's website or confirmed by SMTP, together
+//! with the name of the person it belongs to, shows which pattern template the
+//! company uses (e.g. `jane.smith@acme.com` for Jane Smith means
+//! `{first}.{last}`). The evidence is collected per domain in a
+//! [`FormatStore`] shared by every search, so each contact at a domain
+//! benefits from what earlier contacts revealed.
+
+use crate::cache::VerificationCache;
+use crate::config::PatternTemplate;
+use crate::names::normalize_name_part;
+use crate::patterns::CompiledTemplate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+/// Most addresses and names kept per domain.
+const MAX_ENTRIES_PER_DOMAIN: usize = 500;
+
+/// How an address at a domain became known.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum AddressEvidence {
+    /// Found on the company's website.
+    Scraped,
+    /// Accepted by the domain's mail server, which is not a catch-all.
+    Confirmed,
+}
+
+impl AddressEvidence {
+    /// How much an address with this evidence counts towards a format.
+    fn weight(self) -> u32 {
+        match self {
+            Self::Scraped => 1,
+            Self::Confirmed => 2,
+        }
+    }
+}
+
+/// The sanitized name of a person at a domain.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct KnownName {
+    first: String,
+    middle: Option<String>,
+    last: String,
+}
+
+/// The evidence about the address format of one domain.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub(crate) struct DomainFormat {
+    /// Known local parts, with the strongest evidence for each.
+    addresses: BTreeMap<String, AddressEvidence>,
+    /// Names of people at the domain.
+    names: BTreeSet<KnownName>,
+}
+
+/// The format inferred for a domain.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct InferredFormat {
+    /// The pattern template the domain's addresses follow.
+    pub template: String,
+    /// The known local parts produced by the template.
+    pub examples: Vec<String>,
+}
+
+impl DomainFormat {
+    /// Records a known local part. Returns whether anything changed.
+    fn record_address(&mut self, local_part: &str, evidence: AddressEvidence) -> bool {
+        let local_part = local_part.to_lowercase();
+        let full = self.addresses.len() >= MAX_ENTRIES_PER_DOMAIN;
+        match self.addresses.get_mut(&local_part) {
+            Some(existing) if *existing >= evidence => false,
+            Some(existing) => {
+                *existing = evidence;
+                true
+            }
+            None if full => false,
+            None => {
+                self.addresses.insert(local_part, evidence);
+                true
+            }
+        }
+    }
+
+    /// Records the name of a person at the domain. Returns whether anything
+    /// changed.
+    fn record_name(
+        &mut self,
+        first_name: &str,
+        middle_name: Option<&str>,
+        last_name: &str,
+    ) -> bool {
+        let name = KnownName {
+            first: normalize_name_part(first_name),
+            middle: middle_name
+                .map(normalize_name_part)
+                .filter(|middle| !middle.is_empty()),
+            last: normalize_name_part(last_name),
+        };
+        if name.first.is_empty()
+            || name.last.is_empty()
+            || self.names.len() >= MAX_ENTRIES_PER_DOMAIN
+        {
+            return false;
+        }
+        self.names.insert(name)
+    }
+
+    /// Infers the template the known addresses follow.
+    ///
+    /// Every known address that some template produces for some known name is
+    /// evidence for that template (confirmed addresses count double). The
+    /// template with the most evidence wins; ties go to the template with the
+    /// higher prior weight.
+    ///
+    /// # Returns
+    /// * `Some(InferredFormat)` if at least one known address matches a template.
+    /// * `None` otherwise.
+    pub(crate) fn infer(&self, templates: &[PatternTemplate]) -> Option<InferredFormat> {
+        let mut best: Option<(u32, f32, InferredFormat)> = None;
+        for template in templates {
+            let Ok(compiled) = CompiledTemplate::parse(&template.template) else {
+                continue;
+            };
+            let rendered: HashSet<String> = self
+                .names
+                .iter()
+                .filter_map(|name| compiled.render(&name.first, name.middle.as_deref(), &name.last))
+                .collect();
+            let examples: Vec<(&String, AddressEvidence)> = self
+                .addresses
+                .iter()
+                .filter(|(local_part, _)| rendered.contains(*local_part))
+                .map(|(local_part, evidence)| (local_part, *evidence))
+                .collect();
+            let score: u32 = examples.iter().map(|(_, evidence)| evidence.weight()).sum();
+            if score == 0 {
+                continue;
+            }
+            let better = match &best {
+                None => true,
+                Some((best_score, best_weight, _)) => {
+                    score > *best_score || (score == *best_score && template.weight > *best_weight)
+                }
+            };
+            if better {
+                best = Some((
+                    score,
+                    template.weight,
+                    InferredFormat {
+                        template: template.template.clone(),
+                        examples: examples
+                            .iter()
+                            .map(|(local_part, _)| (*local_part).clone())
+                            .collect(),
+                    },
+                ));
+            }
+        }
+        best.map(|(_, _, format)| format)
+    }
+}
+
+/// Address format evidence for every domain seen, kept in memory for the
+/// lifetime of the finder and written through to the verification cache.
+#[derive(Debug)]
+pub(crate) struct FormatStore {
+    formats: Mutex<HashMap<String, DomainFormat>>,
+    cache: Arc<VerificationCache>,
+}
+
+impl FormatStore {
+    /// Creates an empty store that persists to `cache`.
+    pub(crate) fn new(cache: Arc<VerificationCache>) -> Self {
+        Self {
+            formats: Mutex::new(HashMap::new()),
+            cache,
+        }
+    }
+
+    /// Loads the evidence of `domain` from the cache if it is not in memory
+    /// yet. The cache is read without holding the lock.
+    async fn load(&self, domain: &str) {
+        if self.lock().contains_key(domain) {
+            return;
+        }
+        let cached = self.cache.email_format(domain).await.unwrap_or_default();
+        self.lock().entry(domain.to_string()).or_insert(cached);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, DomainFormat>> {
+        self.formats.lock().expect("format store lock poisoned")
+    }
+
+    /// Applies `update` to the evidence of `domain` and stores it back in the
+    /// cache if `update` changed it.
+    async fn update(&self, domain: &str, update: impl FnOnce(&mut DomainFormat) -> bool) {
+        let domain = domain.to_lowercase();
+        self.load(&domain).await;
+        let changed = {
+            let mut formats = self.lock();
+            let format = formats.entry(domain.clone()).or_default();
+            update(format).then(|| format.clone())
+        };
+        if let Some(format) = changed {
+            self.cache.store_email_format(&domain, &format).await;
+        }
+    }
+
+    /// Records that a person named `first_name` `middle_name` `last_name`
+    /// works at `domain`.
+    pub(crate) async fn record_name(
+        &self,
+        domain: &str,
+        first_name: &str,
+        middle_name: Option<&str>,
+        last_name: &str,
+    ) {
+        self.update(domain, |format| {
+            format.record_name(first_name, middle_name, last_name)
+        })
+        .await;
+    }
+
+    /// Records a known address at `domain`. Addresses at other domains are
+    /// ignored.
+    pub(crate) async fn record_address(
+        &self,
+        domain: &str,
+        email: &str,
+        evidence: AddressEvidence,
+    ) {
+        let Some((local_part, email_domain)) = email.rsplit_once('@') else {
+            return;
+        };
+        if !email_domain.eq_ignore_ascii_case(domain) || local_part.is_empty() {
+            return;
+        }
+        self.update(domain, |format| format.record_address(local_part, evidence))
+            .await;
+    }
+
+    /// Infers the address format of `domain` from the evidence collected so
+    /// far. See [`DomainFormat::infer`]. The templates are matched against a
+    /// snapshot of the evidence, without holding the lock.
+    pub(crate) async fn infer(
+        &self,
+        domain: &str,
+        templates: &[PatternTemplate],
+    ) -> Option<InferredFormat> {
+        let domain = domain.to_lowercase();
+        self.load(&domain).await;
+        let format = self.lock().get(&domain)?.clone();
+        format.infer(templates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::MemoryCache;
+    use crate::config::Config;
+
+    #[tokio::test]
+    async fn test_infer_format_from_known_addresses() {
+        let config = Config::default();
+        let cache = Arc::new(VerificationCache::new(
+            Arc::new(MemoryCache::new()),
+            &config,
+        ));
+        let store = FormatStore::new(cache.clone());
+
+        store.record_name("acme.com", "Jane", None, "Smith").await;
+        store
+            .record_address("acme.com", "info@acme.com", AddressEvidence::Scraped)
+            .await;
+        store
+            .record_address("acme.com", "jane@other.com", AddressEvidence::Scraped)
+            .await;
+        assert_eq!(
+            store.infer("acme.com", &config.pattern_templates).await,
+            None
+        );
+
+        store
+            .record_address("acme.com", "jane.smith@acme.com", AddressEvidence::Scraped)
+            .await;
+        let inferred = store
+            .infer("ACME.com", &config.pattern_templates)
+            .await
+            .unwrap();
+        assert_eq!(inferred.template, "{first}.{last}");
+        assert_eq!(inferred.examples, vec!["jane.smith"]);
+
+        // Confirmed addresses outweigh scraped ones.
+        store.record_name("acme.com", "John", None, "Doe").await;
+        store
+            .record_address("acme.com", "jdoe@acme.com", AddressEvidence::Confirmed)
+            .await;
+        let inferred = store
+            .infer("acme.com", &config.pattern_templates)
+            .await
+            .unwrap();
+        assert_eq!(inferred.template, "{f}{last}");
+
+        // The evidence survives in the cache.
+        let reloaded = FormatStore::new(cache);
+        let inferred = reloaded
+            .infer("acme.com", &config.pattern_templates)
+            .await
+            .unwrap();
+        assert_eq!(inferred.template, "{f}{last}");
+    }
+
+    #[test]
+    fn test_infer_format_with_middle_names() {
+        let config = Config::default();
+        let mut format = DomainFormat::default();
+        format.record_name("John", Some("Quincy"), "Public");
+        format.record_address("jqpublic", AddressEvidence::Confirmed);
+        let inferred = format.infer(&config.pattern_templates).unwrap();
+        assert_eq!(inferred.template, "{f}{m}{last}");
+    }
+}
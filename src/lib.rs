@@ -0,0 +1,55 @@
+//! Library interface for email-sleuth.
+//!
+//! The crate can be embedded in other Tokio services through the
+//! [`EmailSleuth`] type, which is configured explicitly via [`Config`]:
+//!
+//! ```no_run
+//! use email_sleuth::{Config, Contact, EmailSleuth};
+//!
+//! # async fn run() -> email_sleuth::Result<()> {
+//! let sleuth = EmailSleuth::builder()
+//!     .config(Config::default())
+//!     .build()
+//!     .await?;
+//!
+//! let contact: Contact = serde_json::from_str(
+//!     r#"{"first_name": "John", "last_name": "Doe", "domain": "example.com"}"#,
+//! )?;
+//! let result = sleuth.process_record(contact).await;
+//! println!("{:?}", result.email);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod api;
+mod cache;
+mod catch_all;
+pub mod config;
+mod deferred;
+mod dns;
+mod domain;
+pub mod error;
+mod formats;
+#[cfg(test)]
+mod mock_smtp;
+pub mod models;
+mod name_parser;
+mod names;
+mod patterns;
+mod processor;
+mod resolver;
+mod scraper;
+pub mod sleuth;
+mod smtp;
+mod smtp_client;
+
+pub use cache::{CacheBackend, MemoryCache, SqliteCache};
+pub use config::Config;
+pub use deferred::DeferredVerifications;
+pub use error::{AppError, Result};
+pub use models::{
+    Contact, DomainInfo, EmailResult, FoundEmailData, ParsedName, ProcessingResult,
+    ValidatedContact, VerificationStatus,
+};
+pub use resolver::{MailResolver, MxRecord, StaticResolver, TrustDnsResolver};
+pub use sleuth::{EmailSleuth, EmailSleuthBuilder};
@@ -1,25 +1,18 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
+use email_sleuth::{DeferredVerifications, EmailSleuth, api, config, models};
 use std::path::PathBuf;
+use std::sync::Arc;
 use tracing::info;
 
-mod api;
-mod config;
-mod dns;
-mod domain;
-mod error;
-mod models;
-mod patterns;
-mod processor;
-mod scraper;
-mod sleuth;
-mod smtp;
-
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    #[command(flatten)]
+    config: config::AppArgs,
 }
 
 #[derive(Subcommand)]
@@ -51,42 +44,55 @@ async fn main() -> Result<()> {
     // Initialize tracing
     tracing_subscriber::fmt::init();
 
-    // Load configuration
-    config::load_config()?;
-
     let cli = Cli::parse();
 
+    // Load configuration from defaults, config file and CLI overrides
+    let config = config::build_config(&cli.config)?;
+
+    // Create the EmailSleuth instance
+    let sleuth = Arc::new(EmailSleuth::builder().config(config).build().await?);
+
     match cli.command {
         Commands::Process {
             input,
             output,
             workers,
         } => {
-            info!("Processing contacts from {} to {}", input.display(), output.display());
-            process_file(input, output, workers).await?;
+            info!(
+                "Processing contacts from {} to {}",
+                input.display(),
+                output.display()
+            );
+            process_file(sleuth, input, output, workers).await?;
         }
         Commands::Serve { port } => {
             info!("Starting API server on port {}", port);
-            api::start_api_server(port).await?;
+            api::start_api_server(sleuth, port).await?;
         }
     }
 
     Ok(())
 }
 
-async fn process_file(input: PathBuf, output: PathBuf, workers: usize) -> Result<()> {
+async fn process_file(
+    sleuth: Arc<EmailSleuth>,
+    input: PathBuf,
+    output: PathBuf,
+    workers: usize,
+) -> Result<()> {
     // Read the input file
     let input_data = std::fs::read_to_string(&input)?;
     let contacts: Vec<models::Contact> = serde_json::from_str(&input_data)?;
 
-    info!("Loaded {} contacts from {}", contacts.len(), input.display());
-
-    // Create the EmailSleuth instance
-    let sleuth = std::sync::Arc::new(sleuth::EmailSleuth::new().await?);
+    info!(
+        "Loaded {} contacts from {}",
+        contacts.len(),
+        input.display()
+    );
 
     // Process the contacts
     let mut results = Vec::with_capacity(contacts.len());
-    let semaphore = std::sync::Arc::new(tokio::sync::Semaphore::new(workers));
+    let semaphore = Arc::new(tokio::sync::Semaphore::new(workers));
 
     let progress_bar = indicatif::ProgressBar::new(contacts.len() as u64);
     progress_bar.set_style(
@@ -97,15 +103,19 @@ async fn process_file(input: PathBuf, output: PathBuf, workers: usize) -> Result
     );
 
     let mut tasks = Vec::new();
+    let deferred = DeferredVerifications::new();
 
     for contact in contacts {
         let sleuth_clone = sleuth.clone();
         let semaphore_clone = semaphore.clone();
         let progress_bar_clone = progress_bar.clone();
+        let deferred_clone = deferred.clone();
 
         let task = tokio::spawn(async move {
             let _permit = semaphore_clone.acquire().await.unwrap();
-            let result = processor::process_record(sleuth_clone, contact).await;
+            let result = sleuth_clone
+                .process_record_deferring(contact, &deferred_clone)
+                .await;
             progress_bar_clone.inc(1);
             result
         });
@@ -120,6 +130,15 @@ async fn process_file(input: PathBuf, output: PathBuf, workers: usize) -> Result
 
     progress_bar.finish_with_message("Processing complete");
 
+    if !deferred.is_empty() {
+        info!(
+            "Waiting for {} deferred re-verifications of greylisted addresses",
+            deferred.len()
+        );
+        let patched = sleuth.complete_deferred(deferred, &mut results).await;
+        info!("Updated {} results after deferred re-verification", patched);
+    }
+
     // Write the results to the output file
     let output_data = serde_json::to_string_pretty(&results)?;
     std::fs::write(&output, output_data)?;
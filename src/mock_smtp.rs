@@ -0,0 +1,258 @@
+//! A scriptable SMTP server for tests.
+//!
+//! [`MockSmtpServer`] listens on an ephemeral localhost port and answers like
+//! a mail exchanger whose behavior is scripted per recipient, so SMTP
+//! verification and [`EmailSleuth::find_email`](crate::EmailSleuth::find_email)
+//! can be exercised without network access. Point a configuration at it with
+//! [`MockSmtpServer::config`].
+
+use crate::config::{Config, StartTlsMode};
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// How the server answers `RCPT TO` for a recipient.
+#[derive(Debug, Clone)]
+pub(crate) enum RcptBehavior {
+    /// Accept the recipient with `250 2.1.5`.
+    Accept,
+    /// Reply with the given code and text, e.g. `(550, "5.1.1 User unknown")`.
+    Reject(u16, &'static str),
+    /// Defer the first `attempts` RCPTs for the recipient with a greylisting
+    /// reply, then accept it.
+    Greylist { attempts: usize },
+    /// Close the connection without replying.
+    DropConnection,
+    /// Never reply.
+    Stall,
+}
+
+/// How the server treats a new connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum ConnectBehavior {
+    /// Send the `220` greeting and serve the session.
+    #[default]
+    Greet,
+    /// Close the connection before the greeting.
+    DropConnection,
+    /// Accept the connection but never send the greeting.
+    Stall,
+}
+
+/// The behavior of a [`MockSmtpServer`].
+#[derive(Debug, Clone)]
+pub(crate) struct MockSmtpScript {
+    on_connect: ConnectBehavior,
+    mailboxes: HashMap<String, RcptBehavior>,
+    unknown_recipients: RcptBehavior,
+}
+
+impl Default for MockSmtpScript {
+    fn default() -> Self {
+        Self {
+            on_connect: ConnectBehavior::Greet,
+            mailboxes: HashMap::new(),
+            unknown_recipients: RcptBehavior::Reject(550, "5.1.1 User unknown"),
+        }
+    }
+}
+
+impl MockSmtpScript {
+    /// A server that knows no mailboxes and rejects every recipient as unknown.
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how `address` is answered.
+    pub(crate) fn mailbox(mut self, address: &str, behavior: RcptBehavior) -> Self {
+        self.mailboxes.insert(address.to_lowercase(), behavior);
+        self
+    }
+
+    /// Sets how recipients without a scripted mailbox are answered.
+    pub(crate) fn unknown_recipients(mut self, behavior: RcptBehavior) -> Self {
+        self.unknown_recipients = behavior;
+        self
+    }
+
+    /// Accepts every recipient, like a catch-all domain.
+    pub(crate) fn catch_all(self) -> Self {
+        self.unknown_recipients(RcptBehavior::Accept)
+    }
+
+    /// Sets how new connections are treated.
+    pub(crate) fn on_connect(mut self, behavior: ConnectBehavior) -> Self {
+        self.on_connect = behavior;
+        self
+    }
+}
+
+#[derive(Debug)]
+struct MockState {
+    script: MockSmtpScript,
+    rcpt_counts: HashMap<String, usize>,
+    recipients: Vec<String>,
+    connections: usize,
+}
+
+impl MockState {
+    /// Records a RCPT for `address` and returns how to answer it.
+    fn rcpt(&mut self, address: &str) -> RcptBehavior {
+        self.recipients.push(address.to_string());
+        let count = self.rcpt_counts.entry(address.to_string()).or_default();
+        *count += 1;
+        let behavior = self
+            .script
+            .mailboxes
+            .get(address)
+            .unwrap_or(&self.script.unknown_recipients);
+        match behavior {
+            RcptBehavior::Greylist { attempts } if *count > *attempts => RcptBehavior::Accept,
+            other => other.clone(),
+        }
+    }
+}
+
+/// A running scripted SMTP server. The listener stops when it is dropped.
+#[derive(Debug)]
+pub(crate) struct MockSmtpServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    listener_task: JoinHandle<()>,
+}
+
+impl MockSmtpServer {
+    /// Starts a server on an ephemeral localhost port.
+    pub(crate) async fn start(script: MockSmtpScript) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind mock SMTP server");
+        let addr = listener.local_addr().expect("mock SMTP server address");
+        let state = Arc::new(Mutex::new(MockState {
+            script,
+            rcpt_counts: HashMap::new(),
+            recipients: Vec::new(),
+            connections: 0,
+        }));
+
+        let listener_state = state.clone();
+        let listener_task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = listener_state.clone();
+                tokio::spawn(async move {
+                    let _ = serve_connection(stream, state).await;
+                });
+            }
+        });
+
+        Self {
+            addr,
+            state,
+            listener_task,
+        }
+    }
+
+    /// The host to connect to.
+    pub(crate) fn host(&self) -> String {
+        self.addr.ip().to_string()
+    }
+
+    /// The port the server listens on.
+    pub(crate) fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    /// A configuration that sends every probe to this server, with short
+    /// timeouts and no pauses between requests.
+    pub(crate) fn config(&self) -> Config {
+        Config {
+            smtp_host: Some(self.host()),
+            smtp_port: self.port(),
+            smtp_starttls: StartTlsMode::Disabled,
+            smtp_timeout: Duration::from_millis(500),
+            request_timeout: Duration::from_millis(500),
+            sleep_between_requests: (0.0, 0.0),
+            catch_all_probes: 2,
+            common_pages_to_scrape: Vec::new(),
+            fetch_domain_info: false,
+            ..Config::default()
+        }
+    }
+
+    /// Every recipient the server was asked about, in order, including
+    /// catch-all probes.
+    pub(crate) fn recipients(&self) -> Vec<String> {
+        self.state.lock().unwrap().recipients.clone()
+    }
+
+    /// The number of connections accepted so far.
+    pub(crate) fn connections(&self) -> usize {
+        self.state.lock().unwrap().connections
+    }
+}
+
+impl Drop for MockSmtpServer {
+    fn drop(&mut self) {
+        self.listener_task.abort();
+    }
+}
+
+async fn serve_connection(stream: TcpStream, state: Arc<Mutex<MockState>>) -> io::Result<()> {
+    let on_connect = {
+        let mut state = state.lock().unwrap();
+        state.connections += 1;
+        state.script.on_connect
+    };
+    match on_connect {
+        ConnectBehavior::Greet => {}
+        ConnectBehavior::DropConnection => return Ok(()),
+        ConnectBehavior::Stall => std::future::pending::<()>().await,
+    }
+
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    writer.write_all(b"220 mock.test ESMTP ready\r\n").await?;
+
+    while let Some(line) = lines.next_line().await? {
+        let command = line.to_ascii_uppercase();
+        let reply = if command.starts_with("EHLO") {
+            "250-mock.test\r\n250-PIPELINING\r\n250 ENHANCEDSTATUSCODES".to_string()
+        } else if command.starts_with("HELO") {
+            "250 mock.test".to_string()
+        } else if command.starts_with("MAIL FROM") {
+            "250 2.1.0 Sender ok".to_string()
+        } else if command.starts_with("RCPT TO") {
+            let address = line
+                .split_once('<')
+                .and_then(|(_, rest)| rest.split_once('>'))
+                .map(|(address, _)| address.to_lowercase())
+                .unwrap_or_default();
+            let behavior = state.lock().unwrap().rcpt(&address);
+            match behavior {
+                RcptBehavior::Accept => "250 2.1.5 Recipient ok".to_string(),
+                RcptBehavior::Reject(code, text) => format!("{} {}", code, text),
+                RcptBehavior::Greylist { .. } => {
+                    "450 4.7.1 Greylisted, please try again later".to_string()
+                }
+                RcptBehavior::DropConnection => return Ok(()),
+                RcptBehavior::Stall => std::future::pending().await,
+            }
+        } else if command.starts_with("RSET") || command.starts_with("NOOP") {
+            "250 2.0.0 Ok".to_string()
+        } else if command.starts_with("QUIT") {
+            writer.write_all(b"221 2.0.0 Bye\r\n").await?;
+            return Ok(());
+        } else {
+            "502 5.5.2 Command not recognized".to_string()
+        };
+        writer
+            .write_all(format!("{}\r\n", reply).as_bytes())
+            .await?;
+    }
+    Ok(())
+}
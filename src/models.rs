@@ -7,9 +7,12 @@ use url::Url; // Import Url type
 /// Represents the input contact record read from the JSON file.
 /// Allows for flexibility if some fields are missing.
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub(crate) struct Contact {
+pub struct Contact {
     /// The contact's first name.
     pub first_name: Option<String>,
+    /// The contact's middle names or initials (optional input, otherwise
+    /// taken from `full_name`).
+    pub middle_name: Option<String>,
     /// The contact's last name.
     pub last_name: Option<String>,
     /// The contact's full name (optional input).
@@ -27,7 +30,7 @@ pub(crate) struct Contact {
 
 /// Represents a single email address found and its associated metadata.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
-pub(crate) struct FoundEmailData {
+pub struct FoundEmailData {
     /// The discovered email address.
     pub email: String,
     /// A score indicating the likelihood of this email being correct (0-10).
@@ -36,16 +39,69 @@ pub(crate) struct FoundEmailData {
     pub source: String, // Could be an enum: Source { Pattern, Scraped }
     /// Indicates if the email address uses a common generic prefix (e.g., info@, contact@).
     pub is_generic: bool,
+    /// The spelling of the name a pattern candidate was generated from, if it
+    /// is not the name as given: a nickname (e.g. "bob" for Robert) or another
+    /// transliteration (e.g. "mueller" for Müller).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_variant: Option<String>,
+    /// The outcome of the SMTP verification attempt.
+    #[serde(default)]
+    pub verification: VerificationStatus,
     /// The result of the SMTP verification attempt (True=Verified, False=Rejected, None=Inconclusive/Untested).
+    /// Derived from `verification`; kept for compatibility.
     pub verification_status: Option<bool>,
     /// A message accompanying the verification status (e.g., error details, OK message).
     pub verification_message: String,
 }
 
+/// The outcome of verifying a single email address.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationStatus {
+    /// The mail server accepted the address.
+    Valid,
+    /// The mail server rejected the address as unknown.
+    Invalid,
+    /// The domain accepts any address, so acceptance proves nothing.
+    CatchAll,
+    /// The server deferred the address; a later attempt may succeed.
+    Greylisted,
+    /// The mailbox exists but is over quota.
+    MailboxFull,
+    /// The mailbox exists but is disabled.
+    Disabled,
+    /// The server refused the probe for policy reasons or because our
+    /// sending address is blocklisted.
+    PolicyBlocked,
+    /// No mail server could be reached on port 25.
+    Port25Blocked,
+    /// The domain's mail servers could not be resolved.
+    DnsFailed,
+    /// The mail server did not answer in time.
+    Timeout,
+    /// The probe failed for another reason.
+    Inconclusive,
+    /// No verification was attempted.
+    #[default]
+    NotAttempted,
+}
+
+impl VerificationStatus {
+    /// Whether the mailbox exists: `Some(true)` if it does, `Some(false)` if it
+    /// does not, `None` if the status does not say.
+    pub fn exists(self) -> Option<bool> {
+        match self {
+            Self::Valid | Self::MailboxFull => Some(true),
+            Self::Invalid | Self::Disabled => Some(false),
+            _ => None,
+        }
+    }
+}
+
 /// Contains the results of the email finding process for a single contact.
 /// This structure will be added to the original Contact data before saving.
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
-pub(crate) struct EmailResult {
+pub struct EmailResult {
     /// A list of all potentially valid emails found, ordered by likelihood.
     pub found_emails: Vec<FoundEmailData>,
     /// The single email address deemed most likely to be correct.
@@ -56,11 +112,132 @@ pub(crate) struct EmailResult {
     pub methods_used: Vec<String>,
     /// A log of verification attempts and their outcomes for specific emails.
     pub verification_log: HashMap<String, String>,
+    /// How likely it is that the domain accepts any address (0.0 to 1.0), if
+    /// it was probed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub catch_all_confidence: Option<f32>,
+    /// The mail provider identified from the domain's MX records, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mail_provider: Option<String>,
+    /// Whether the domain publishes a null MX (RFC 7505) and so accepts no
+    /// email. Every candidate is then reported as invalid without verification.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub accepts_no_email: bool,
+    /// What the domain's SPF, DMARC and MTA-STS records say about its mail, if
+    /// they were looked up.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain_info: Option<DomainInfo>,
+}
+
+/// Mail-related DNS records published by a domain.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct DomainInfo {
+    /// The domain's SPF policy, from its `v=spf1` TXT record.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spf: Option<SpfRecord>,
+    /// The domain's DMARC policy, from the `_dmarc` TXT record.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dmarc: Option<DmarcRecord>,
+    /// The domain's MTA-STS policy, from the `_mta-sts` TXT record and the
+    /// policy file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mta_sts: Option<MtaStsInfo>,
+    /// Whether the domain publishes a null MX record (RFC 7505), i.e. states
+    /// that it accepts no mail.
+    #[serde(default)]
+    pub null_mx: bool,
+}
+
+impl DomainInfo {
+    /// Whether the domain declares that it neither sends nor receives mail: an
+    /// SPF record authorizing no senders (`v=spf1 -all`) and a null MX.
+    pub fn declares_no_mail(&self) -> bool {
+        self.null_mx
+            && self
+                .spf
+                .as_ref()
+                .is_some_and(SpfRecord::authorizes_no_senders)
+    }
+}
+
+/// A parsed SPF record.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpfRecord {
+    /// The raw TXT record.
+    pub record: String,
+    /// Domains included with `include:`, typically the services sending
+    /// mail for the domain (e.g. `_spf.google.com`, `sendgrid.net`).
+    pub includes: Vec<String>,
+    /// The other mechanisms and modifiers, in order, excluding `include:`
+    /// and `all`.
+    pub mechanisms: Vec<String>,
+    /// The `all` mechanism with its qualifier (e.g. `"-all"`, `"~all"`), if present.
+    pub all: Option<String>,
+}
+
+impl SpfRecord {
+    /// Whether the record authorizes no senders at all (`v=spf1 -all`).
+    pub fn authorizes_no_senders(&self) -> bool {
+        self.includes.is_empty()
+            && self.mechanisms.iter().all(|m| m.contains('='))
+            && self.all.as_deref() == Some("-all")
+    }
+}
+
+/// A parsed DMARC record.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct DmarcRecord {
+    /// The raw TXT record.
+    pub record: String,
+    /// The requested policy (`p=`): `"none"`, `"quarantine"` or `"reject"`.
+    pub policy: Option<String>,
+    /// The policy for subdomains (`sp=`), if different.
+    pub subdomain_policy: Option<String>,
+    /// The percentage of messages the policy applies to (`pct=`).
+    pub percentage: Option<u8>,
+    /// Addresses aggregate reports are sent to (`rua=`).
+    pub aggregate_reports: Vec<String>,
+}
+
+/// A domain's MTA-STS configuration.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct MtaStsInfo {
+    /// The policy id from the `_mta-sts` TXT record.
+    pub id: Option<String>,
+    /// The policy mode: `"enforce"`, `"testing"` or `"none"`. `None` if the
+    /// policy file could not be fetched.
+    pub mode: Option<String>,
+    /// The mail exchanger patterns the policy allows.
+    pub mx: Vec<String>,
+    /// How long senders may cache the policy, in seconds.
+    pub max_age: Option<u64>,
+}
+
+/// The components of a contact's full name, as parsed from `full_name`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedName {
+    /// Titles before the name (e.g. "Dr.").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub honorific: Option<String>,
+    /// The first given name. Empty if the name had no words.
+    pub first: String,
+    /// Further given names (e.g. "Quincy" or "Q").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub middle: Option<String>,
+    /// The uppercase initial of the middle name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub middle_initial: Option<String>,
+    /// The surname, including particles such as "van" or "de la".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last: Option<String>,
+    /// Generational or academic suffixes after the name (e.g. "Jr.", "PhD").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suffix: Option<String>,
 }
 
 /// Represents the final output structure for each record, combining input and results.
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub(crate) struct ProcessingResult {
+pub struct ProcessingResult {
     // Include all fields from the original Contact input
     #[serde(flatten)]
     pub contact_input: Contact,
@@ -74,6 +251,10 @@ pub(crate) struct ProcessingResult {
     /// Confidence score for the primary email (convenience field).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub email_confidence: Option<u8>,
+    /// Verification outcome for the primary email (convenience field).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub email_verification: Option<VerificationStatus>,
     /// A comma-separated list of methods used (convenience field).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub email_verification_method: Option<String>,
@@ -97,12 +278,18 @@ pub(crate) struct ProcessingResult {
     /// Error message if processing failed unexpectedly.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub email_finding_error: Option<String>,
+    /// The components parsed from the input's `full_name`, if it had one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub parsed_name: Option<ParsedName>,
 }
 
 /// Internal representation after validating input Contact
 #[derive(Debug, Clone)]
-pub(crate) struct ValidatedContact {
+pub struct ValidatedContact {
     pub first_name: String,
+    /// Middle names or initials, if known.
+    pub middle_name: Option<String>,
     pub last_name: String,
     /// Guaranteed to be populated (either from input or constructed).
     pub full_name: String,
@@ -114,47 +301,117 @@ pub(crate) struct ValidatedContact {
     pub original_contact: Contact,
 }
 
+/// What an SMTP reply says about a recipient, derived from the basic reply
+/// code and the RFC 3463 enhanced status code.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SmtpVerdict {
+    /// The server accepted the recipient.
+    Accepted,
+    /// The mailbox does not exist (e.g. `5.1.1`).
+    UnknownUser,
+    /// The mailbox exists but is disabled (`5.2.1`).
+    MailboxDisabled,
+    /// The mailbox exists but is over quota (`x.2.2`).
+    MailboxFull,
+    /// The mailbox is temporarily unavailable (`4.2.1`).
+    MailboxBusy,
+    /// The server refused the probe for policy reasons (`x.7.x`).
+    PolicyBlock,
+    /// The probe was refused because our sending address is on a blocklist.
+    Blacklisted,
+    /// The server deferred the recipient, asking us to come back later.
+    Greylisted,
+    /// Any other 4xx reply.
+    TemporaryFailure,
+    /// Any other 5xx reply.
+    Rejected,
+    /// No usable reply was received.
+    Unknown,
+}
+
 /// Internal representation of SMTP verification outcome
-#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub(crate) struct SmtpVerificationResult {
-    /// True = Exists, False = Does Not Exist, None = Inconclusive/Error
-    pub exists: Option<bool>,
+    /// The verification outcome. See [`SmtpVerificationResult::exists`].
+    pub status: VerificationStatus,
     /// Detailed message about the outcome.
     pub message: String,
     /// Suggests if retrying might yield a different result (e.g., for temporary errors).
     pub should_retry: bool,
-    /// Indicates if the domain seems to accept all emails.
-    pub is_catch_all: bool,
+    /// How likely it is that the domain accepts any address (0.0 to 1.0), if
+    /// the session got far enough to probe it.
+    pub catch_all_confidence: Option<f32>,
+    /// The mail exchanger that answered the probe, if any did.
+    pub mail_server: Option<String>,
+    /// Whether the session was upgraded to TLS with STARTTLS.
+    pub tls_used: bool,
+    /// Subject of the certificate presented during the TLS handshake.
+    pub tls_certificate_subject: Option<String>,
+    /// The classified server reply this result is based on.
+    pub verdict: SmtpVerdict,
 }
 
 impl SmtpVerificationResult {
     /// Creates a conclusive result (email definitely exists or not).
-    pub(crate) fn conclusive(exists: bool, message: String, is_catch_all: bool) -> Self {
+    pub(crate) fn conclusive(exists: bool, message: String) -> Self {
         Self {
-            exists: Some(exists),
+            status: if exists {
+                VerificationStatus::Valid
+            } else {
+                VerificationStatus::Invalid
+            },
             message,
             should_retry: false,
-            is_catch_all,
+            catch_all_confidence: None,
+            mail_server: None,
+            tls_used: false,
+            tls_certificate_subject: None,
+            verdict: SmtpVerdict::Unknown,
         }
     }
 
     /// Creates an inconclusive result where retrying might help.
     pub(crate) fn inconclusive_retry(message: String) -> Self {
         Self {
-            exists: None,
+            status: VerificationStatus::Inconclusive,
             message,
             should_retry: true,
-            is_catch_all: false,
+            catch_all_confidence: None,
+            mail_server: None,
+            tls_used: false,
+            tls_certificate_subject: None,
+            verdict: SmtpVerdict::Unknown,
         }
     }
 
     /// Creates an inconclusive result where retrying is unlikely to help.
     pub(crate) fn inconclusive_no_retry(message: String) -> Self {
         Self {
-            exists: None,
+            status: VerificationStatus::Inconclusive,
             message,
             should_retry: false,
-            is_catch_all: false,
+            catch_all_confidence: None,
+            mail_server: None,
+            tls_used: false,
+            tls_certificate_subject: None,
+            verdict: SmtpVerdict::Unknown,
         }
     }
+
+    /// Sets the reply classification behind this result.
+    pub(crate) fn with_verdict(mut self, verdict: SmtpVerdict) -> Self {
+        self.verdict = verdict;
+        self
+    }
+
+    /// Refines the verification status set by the constructor.
+    pub(crate) fn with_status(mut self, status: VerificationStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// True = Exists, False = Does Not Exist, None = Inconclusive/Error
+    pub(crate) fn exists(&self) -> Option<bool> {
+        self.status.exists()
+    }
 }
@@ -0,0 +1,248 @@
+//! Parsing of free-form personal names into their components.
+//!
+//! Contacts often come with only a `full_name` such as `Dr. Maria de la Cruz
+//! Jr.` or `Smith, John Q.`. The parser strips honorifics and generational or
+//! academic suffixes, accepts the `Last, First Middle` order, and keeps
+//! surname particles (`van`, `de la`, `bin`, ...) with the surname they
+//! belong to, so `Ludwig van Beethoven` has the last name `van Beethoven`.
+
+use crate::models::ParsedName;
+
+/// Titles written before a name. `Sr` is left out: it is read as the
+/// suffix `Senior`, not the Spanish `Señor`.
+const HONORIFICS: &[&str] = &[
+    "mr", "mrs", "ms", "miss", "mx", "dr", "prof", "sir", "dame", "rev", "hon", "capt", "herr",
+    "frau", "mme", "mlle", "sra", "srta",
+];
+
+/// Generational and academic suffixes written after a name.
+const SUFFIXES: &[&str] = &[
+    "jr", "sr", "ii", "iii", "iv", "phd", "md", "esq", "mba", "cpa", "dds", "jd", "obe", "mbe",
+];
+
+/// Words that start a multi-word surname.
+const PARTICLES: &[&str] = &[
+    "van", "von", "der", "den", "de", "del", "della", "di", "da", "du", "la", "le", "bin", "binti",
+    "bint", "ibn", "abu", "al", "el", "dos", "das", "do", "ter", "ten", "st", "saint",
+];
+
+/// The lowercase form of a name token without dots, for matching against the
+/// word lists (`Ph.D.` → `phd`).
+fn token_key(token: &str) -> String {
+    token
+        .chars()
+        .filter(|c| *c != '.')
+        .collect::<String>()
+        .to_lowercase()
+}
+
+fn is_honorific(token: &str) -> bool {
+    HONORIFICS.contains(&token_key(token).as_str())
+}
+
+fn is_suffix(token: &str) -> bool {
+    SUFFIXES.contains(&token_key(token).as_str())
+}
+
+fn is_particle(token: &str) -> bool {
+    PARTICLES.contains(&token_key(token).as_str())
+}
+
+/// Drops the dot after an initial (`Q.` → `Q`).
+fn clean_token(token: &str) -> String {
+    match token.strip_suffix('.') {
+        Some(initial) if initial.chars().count() == 1 => initial.to_string(),
+        _ => token.to_string(),
+    }
+}
+
+/// Removes honorifics from the front of `tokens`, keeping at least one token.
+fn strip_honorifics<'a>(tokens: &mut Vec<&'a str>, honorifics: &mut Vec<&'a str>) {
+    while tokens.len() > 1 && is_honorific(tokens[0]) {
+        honorifics.push(tokens.remove(0));
+    }
+}
+
+/// Removes suffixes from the end of `tokens`, keeping at least one token.
+fn strip_suffixes<'a>(tokens: &mut Vec<&'a str>, suffixes: &mut Vec<&'a str>) {
+    while tokens.len() > 1 && tokens.last().is_some_and(|token| is_suffix(token)) {
+        suffixes.insert(0, tokens.pop().unwrap_or_default());
+    }
+}
+
+fn join(tokens: &[&str]) -> Option<String> {
+    (!tokens.is_empty()).then(|| {
+        tokens
+            .iter()
+            .map(|token| clean_token(token))
+            .collect::<Vec<_>>()
+            .join(" ")
+    })
+}
+
+/// Returns the surname without its particles (`van Beethoven` → `Beethoven`,
+/// `al-Rashid` → `Rashid`), or `None` if it has none. A surname of several
+/// words that are not all particles (`Garcia Lopez`) has no core.
+pub(crate) fn surname_core(last_name: &str) -> Option<&str> {
+    let mut words: Vec<&str> = last_name.split_whitespace().collect();
+    let final_word = words.pop()?;
+    if !words.iter().all(|word| is_particle(word)) {
+        return None;
+    }
+    let core = match final_word.split_once('-') {
+        Some((particle, rest)) if is_particle(particle) && !rest.is_empty() => rest,
+        _ => final_word,
+    };
+    (core != last_name.trim()).then_some(core)
+}
+
+/// Parses a full name into honorific, first, middle and last name, and
+/// suffix.
+///
+/// # Arguments
+/// * `full_name` - The name as written, in `First Middle Last` or
+///   `Last, First Middle` order.
+///
+/// # Returns
+/// * `ParsedName` with the components found. A name of a single word yields
+///   only a first name; an empty name yields an empty `first`.
+pub(crate) fn parse_full_name(full_name: &str) -> ParsedName {
+    let mut segments: Vec<Vec<&str>> = full_name
+        .split(',')
+        .map(|segment| segment.split_whitespace().collect::<Vec<_>>())
+        .filter(|tokens| !tokens.is_empty())
+        .collect();
+
+    let mut honorifics = Vec::new();
+    let mut suffixes = Vec::new();
+    // Trailing segments made of suffixes only, as in `John Smith, Jr.`.
+    while segments.len() > 1
+        && segments
+            .last()
+            .is_some_and(|tokens| tokens.iter().all(|token| is_suffix(token)))
+    {
+        let mut trailing = segments.pop().unwrap_or_default();
+        trailing.append(&mut suffixes);
+        suffixes = trailing;
+    }
+
+    let (given, surname) = if segments.len() >= 2 {
+        // `Last, First Middle`.
+        let mut surname = segments.remove(0);
+        let mut given: Vec<&str> = segments.concat();
+        strip_honorifics(&mut surname, &mut honorifics);
+        strip_honorifics(&mut given, &mut honorifics);
+        strip_suffixes(&mut surname, &mut suffixes);
+        strip_suffixes(&mut given, &mut suffixes);
+        (given, surname)
+    } else {
+        let mut tokens = segments.pop().unwrap_or_default();
+        strip_honorifics(&mut tokens, &mut honorifics);
+        strip_suffixes(&mut tokens, &mut suffixes);
+        if tokens.len() < 2 {
+            (tokens, Vec::new())
+        } else {
+            // The surname starts at the first particle followed by a
+            // non-particle, or is the last word.
+            let start = (1..tokens.len() - 1)
+                .find(|&i| {
+                    is_particle(tokens[i]) && tokens[i + 1..].iter().any(|t| !is_particle(t))
+                })
+                .unwrap_or(tokens.len() - 1);
+            let surname = tokens.split_off(start);
+            (tokens, surname)
+        }
+    };
+
+    let first = given
+        .first()
+        .map(|token| clean_token(token))
+        .unwrap_or_default();
+    let middle = join(given.get(1..).unwrap_or_default());
+    let middle_initial = middle
+        .as_deref()
+        .and_then(|middle| middle.chars().next())
+        .map(|initial| initial.to_uppercase().to_string());
+
+    ParsedName {
+        honorific: join(&honorifics),
+        first,
+        middle,
+        middle_initial,
+        last: join(&surname),
+        suffix: join(&suffixes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts(name: &str) -> (String, Option<String>, Option<String>) {
+        let parsed = parse_full_name(name);
+        (parsed.first, parsed.middle, parsed.last)
+    }
+
+    fn some(value: &str) -> Option<String> {
+        Some(value.to_string())
+    }
+
+    #[test]
+    fn test_parse_full_name() {
+        let parsed = parse_full_name("Dr. Maria de la Cruz Jr.");
+        assert_eq!(parsed.honorific, some("Dr."));
+        assert_eq!(parsed.first, "Maria");
+        assert_eq!(parsed.middle, None);
+        assert_eq!(parsed.last, some("de la Cruz"));
+        assert_eq!(parsed.suffix, some("Jr."));
+
+        let parsed = parse_full_name("Smith, John Q.");
+        assert_eq!(parsed.first, "John");
+        assert_eq!(parsed.middle, some("Q"));
+        assert_eq!(parsed.middle_initial, some("Q"));
+        assert_eq!(parsed.last, some("Smith"));
+
+        let parsed = parse_full_name("Prof. Dr. Hans Peter Müller, PhD");
+        assert_eq!(parsed.honorific, some("Prof. Dr."));
+        assert_eq!(parsed.suffix, some("PhD"));
+        assert_eq!(
+            parts("Prof. Dr. Hans Peter Müller, PhD"),
+            ("Hans".to_string(), some("Peter"), some("Müller"))
+        );
+
+        assert_eq!(
+            parts("Ludwig van Beethoven"),
+            ("Ludwig".to_string(), None, some("van Beethoven"))
+        );
+        assert_eq!(
+            parts("Abdullah bin Said"),
+            ("Abdullah".to_string(), None, some("bin Said"))
+        );
+        assert_eq!(
+            parts("Juan Carlos de la Cruz"),
+            ("Juan".to_string(), some("Carlos"), some("de la Cruz"))
+        );
+        assert_eq!(
+            parts("van der Berg, Anna"),
+            ("Anna".to_string(), None, some("van der Berg"))
+        );
+        assert_eq!(
+            parts("Omar al-Rashid"),
+            ("Omar".to_string(), None, some("al-Rashid"))
+        );
+        assert_eq!(surname_core("al-Rashid"), Some("Rashid"));
+        assert_eq!(surname_core("van der Berg"), Some("Berg"));
+        assert_eq!(surname_core("Smith-Jones"), None);
+        assert_eq!(surname_core("de la Cruz"), Some("Cruz"));
+        assert_eq!(surname_core("Garcia Lopez"), None);
+        // A particle as the last word is the surname itself.
+        assert_eq!(parts("Rosa Le"), ("Rosa".to_string(), None, some("Le")));
+        let parsed = parse_full_name("John Smith Sr");
+        assert_eq!(parsed.honorific, None);
+        assert_eq!(parsed.last, some("Smith"));
+        assert_eq!(parsed.suffix, some("Sr"));
+        assert_eq!(parse_full_name("Smith, John, Sr.").suffix, some("Sr."));
+        assert_eq!(parts("Madonna"), ("Madonna".to_string(), None, None));
+        assert_eq!(parts("  "), (String::new(), None, None));
+    }
+}
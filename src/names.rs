@@ -0,0 +1,276 @@
+//! Normalization of personal names into the ASCII forms used in addresses.
+//!
+//! Names are transliterated to ASCII, so `José Müller` becomes `jose` and
+//! `muller`. Because companies spell such names differently, every plausible
+//! form is produced: `ü` as both `u` and `ue`, hyphenated names with and
+//! without the hyphen, and Cyrillic names in both the strict and the common
+//! passport romanization (`Дмитрий` as `dmitrii` and `dmitry`). CJK names are
+//! romanized syllable by syllable.
+//!
+//! First names are also expanded to the nicknames people use at work (Robert
+//! as `bob` or `rob`), from a bundled dictionary that can be extended.
+
+use crate::error::{AppError, Result};
+use deunicode::deunicode_char;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+
+/// The bundled nickname dictionary, keyed by canonical first name.
+static BUNDLED_NICKNAMES: Lazy<HashMap<String, Vec<String>>> = Lazy::new(|| {
+    parse_nicknames(include_str!("nicknames.txt")).expect("bundled nickname list is valid")
+});
+
+/// Most variants produced for one name part.
+const MAX_VARIANTS: usize = 4;
+
+/// German and Scandinavian letters with a two-letter spelling in addresses.
+const EXPANDED_LETTERS: &[(char, &str)] = &[
+    ('ä', "ae"),
+    ('ö', "oe"),
+    ('ü', "ue"),
+    ('ø', "oe"),
+    ('å', "aa"),
+    ('æ', "ae"),
+    ('ß', "ss"),
+];
+
+/// Cyrillic letters whose common romanization differs from the strict one.
+const CYRILLIC_COMMON: &[(char, &str)] = &[
+    ('й', "y"),
+    ('ю', "yu"),
+    ('я', "ya"),
+    ('ё', "e"),
+    ('ъ', ""),
+    ('ь', ""),
+];
+
+/// Transliterates `name` to ASCII, using `overrides` before the default
+/// transliteration of each character.
+fn transliterate(name: &str, overrides: &[(char, &str)]) -> String {
+    name.chars()
+        .map(
+            |c| match overrides.iter().find(|(letter, _)| *letter == c) {
+                Some((_, replacement)) => replacement.to_string(),
+                None if c.is_ascii() => c.to_string(),
+                None => deunicode_char(c).unwrap_or_default().to_string(),
+            },
+        )
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Romanizes Cyrillic the way names are commonly spelled in passports and
+/// addresses (`Дмитрий` → `dmitry`, `Юлия` → `yulia`).
+fn cyrillic_common(name: &str) -> String {
+    let words: Vec<String> = name
+        .split_whitespace()
+        .map(|word| {
+            if let Some(stem) = word.strip_suffix("ий").or_else(|| word.strip_suffix("ый")) {
+                format!("{}y", transliterate(stem, CYRILLIC_COMMON))
+            } else if let Some(stem) = word.strip_suffix("ия") {
+                format!("{}ia", transliterate(stem, CYRILLIC_COMMON))
+            } else {
+                transliterate(word, CYRILLIC_COMMON)
+            }
+        })
+        .collect();
+    words.join(" ")
+}
+
+/// Keeps only the characters allowed in an address, optionally keeping
+/// hyphens between name parts.
+fn to_local_part(name: &str, keep_hyphens: bool) -> String {
+    let mut result = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            result.push(c);
+        } else if c == '-' && keep_hyphens && !result.is_empty() && !result.ends_with('-') {
+            result.push('-');
+        }
+    }
+    result.trim_end_matches('-').to_string()
+}
+
+/// Returns the ASCII forms of one name part (first, middle or last name) that
+/// may appear in addresses, most likely first.
+///
+/// # Arguments
+/// * `part` - The name part as written, e.g. `"Müller"` or `"Smith-Jones"`.
+///
+/// # Returns
+/// * `Vec<String>` of lowercase variants containing only ASCII letters,
+///   digits and hyphens, without duplicates. Empty if nothing of the name
+///   can be transliterated.
+pub(crate) fn name_variants(part: &str) -> Vec<String> {
+    let name = part.trim().to_lowercase();
+    let mut transliterations = vec![transliterate(&name, &[])];
+    if name
+        .chars()
+        .any(|c| EXPANDED_LETTERS.iter().any(|(l, _)| *l == c))
+    {
+        transliterations.push(transliterate(&name, EXPANDED_LETTERS));
+    }
+    if name.chars().any(|c| matches!(c, '\u{0400}'..='\u{04FF}')) {
+        transliterations.push(cyrillic_common(&name));
+    }
+
+    let mut variants: Vec<String> = Vec::new();
+    for keep_hyphens in [false, true] {
+        for transliteration in &transliterations {
+            let variant = to_local_part(transliteration, keep_hyphens);
+            if !variant.is_empty() && !variants.contains(&variant) {
+                variants.push(variant);
+            }
+        }
+    }
+    variants.truncate(MAX_VARIANTS);
+    variants
+}
+
+/// Returns the most likely ASCII form of a name part, or an empty string if
+/// nothing of it can be transliterated. See [`name_variants`].
+pub(crate) fn normalize_name_part(part: &str) -> String {
+    name_variants(part).into_iter().next().unwrap_or_default()
+}
+
+/// Returns the bundled nickname dictionary.
+pub(crate) fn bundled_nicknames() -> HashMap<String, Vec<String>> {
+    BUNDLED_NICKNAMES.clone()
+}
+
+/// Parses a nickname dictionary: one `canonical: nickname, nickname` line per
+/// first name. Blank lines and lines starting with `#` are ignored.
+///
+/// # Returns
+/// * `Ok(HashMap)` from normalized canonical names to their nicknames.
+/// * `Err(AppError::Config)` naming the first malformed line.
+pub(crate) fn parse_nicknames(text: &str) -> Result<HashMap<String, Vec<String>>> {
+    let mut nicknames: HashMap<String, Vec<String>> = HashMap::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (canonical, short_forms) = line.split_once(':').ok_or_else(|| {
+            AppError::Config(format!(
+                "Nickname line {} is not 'name: nickname, ...': {}",
+                number + 1,
+                line
+            ))
+        })?;
+        let canonical = normalize_name_part(canonical);
+        if canonical.is_empty() {
+            return Err(AppError::Config(format!(
+                "Nickname line {} has no name: {}",
+                number + 1,
+                line
+            )));
+        }
+        let entry = nicknames.entry(canonical).or_default();
+        for nickname in short_forms.split(',').map(normalize_name_part) {
+            if !nickname.is_empty() && !entry.contains(&nickname) {
+                entry.push(nickname);
+            }
+        }
+    }
+    Ok(nicknames)
+}
+
+/// Adds the entries of `extra` to `nicknames`.
+pub(crate) fn merge_nicknames(
+    nicknames: &mut HashMap<String, Vec<String>>,
+    extra: HashMap<String, Vec<String>>,
+) {
+    for (canonical, short_forms) in extra {
+        let entry = nicknames.entry(canonical).or_default();
+        for nickname in short_forms {
+            if !entry.contains(&nickname) {
+                entry.push(nickname);
+            }
+        }
+    }
+}
+
+/// Returns the other names a person called `first_name` may go by: the
+/// nicknames of a canonical name, or the canonical name of a nickname
+/// (`bob` → `robert`).
+///
+/// # Arguments
+/// * `first_name` - A normalized first name, as returned by [`name_variants`].
+/// * `nicknames` - The nickname dictionary.
+pub(crate) fn alternative_first_names(
+    first_name: &str,
+    nicknames: &HashMap<String, Vec<String>>,
+) -> Vec<String> {
+    let mut alternatives: Vec<String> = nicknames.get(first_name).cloned().unwrap_or_default();
+    let mut canonical_names: Vec<&String> = nicknames
+        .iter()
+        .filter(|(_, short_forms)| short_forms.iter().any(|n| n == first_name))
+        .map(|(canonical, _)| canonical)
+        .collect();
+    canonical_names.sort();
+    for canonical in canonical_names {
+        if !alternatives.contains(canonical) {
+            alternatives.push(canonical.clone());
+        }
+    }
+    alternatives.retain(|name| name != first_name);
+    alternatives
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_european_names() {
+        assert_eq!(name_variants("José"), vec!["jose"]);
+        assert_eq!(name_variants("Müller"), vec!["muller", "mueller"]);
+        assert_eq!(name_variants("Søren"), vec!["soren", "soeren"]);
+        assert_eq!(name_variants("Weiß"), vec!["weiss"]);
+        assert_eq!(name_variants("François"), vec!["francois"]);
+        assert_eq!(name_variants("Łukasz"), vec!["lukasz"]);
+        assert_eq!(name_variants("O'Brien"), vec!["obrien"]);
+        assert_eq!(name_variants("D’Angelo"), vec!["dangelo"]);
+        assert_eq!(
+            name_variants("Smith-Jones"),
+            vec!["smithjones", "smith-jones"]
+        );
+        assert_eq!(name_variants(" Van Der Beek "), vec!["vanderbeek"]);
+        assert_eq!(
+            name_variants("Göran-Öberg"),
+            vec!["goranoberg", "goeranoeberg", "goran-oberg", "goeran-oeberg"]
+        );
+    }
+
+    #[test]
+    fn test_cyrillic_and_cjk_names() {
+        assert_eq!(name_variants("Дмитрий"), vec!["dmitrii", "dmitry"]);
+        assert_eq!(name_variants("Юлия"), vec!["iuliia", "yulia"]);
+        assert_eq!(name_variants("Хрущёв"), vec!["khrushchiov", "khrushchev"]);
+        assert_eq!(name_variants("王小明"), vec!["wangxiaoming"]);
+        assert_eq!(normalize_name_part("さくら"), "sakura");
+        assert!(name_variants("-'").is_empty());
+    }
+
+    #[test]
+    fn test_nicknames() {
+        let mut nicknames = bundled_nicknames();
+        assert_eq!(
+            alternative_first_names("robert", &nicknames)[..2],
+            ["bob", "rob"]
+        );
+        assert_eq!(
+            alternative_first_names("liz", &nicknames),
+            vec!["elizabeth"]
+        );
+        assert!(alternative_first_names("zyxw", &nicknames).is_empty());
+
+        let extra =
+            parse_nicknames("# local additions\nRoberto: Beto\nrobert: bert, rab\n").unwrap();
+        merge_nicknames(&mut nicknames, extra);
+        assert_eq!(alternative_first_names("roberto", &nicknames), vec!["beto"]);
+        assert!(alternative_first_names("robert", &nicknames).contains(&"rab".to_string()));
+        assert!(parse_nicknames("robert bob").is_err());
+    }
+}
@@ -1,28 +1,253 @@
 //! Generates potential email address patterns based on names and domain.
+//!
+//! Candidates are rendered from the configured
+//! [`PatternTemplate`](crate::config::PatternTemplate)s, each of which carries
+//! a prior weight saying how common the format is.
 
-use crate::config::CONFIG;
-use std::collections::HashSet;
+use crate::config::Config;
+use crate::error::{AppError, Result};
+use crate::name_parser::surname_core;
+use crate::names::{alternative_first_names, name_variants, normalize_name_part};
+use std::collections::HashMap;
 
-/// Removes whitespace and converts to lowercase.
-fn sanitize_name_part(part: &str) -> String {
-    part.trim().replace(char::is_whitespace, "").to_lowercase()
+/// The most confidence points a pattern's prior weight can contribute.
+const MAX_PATTERN_BONUS: f32 = 5.0;
+
+/// Most nicknames of a first name candidates are generated for, taken in
+/// dictionary order (the most common first).
+const MAX_NICKNAMES: usize = 2;
+
+/// A candidate address generated from a pattern template.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PatternCandidate {
+    /// The generated address.
+    pub email: String,
+    /// The template the address was rendered from.
+    pub template: String,
+    /// The prior weight of the template, lowered for nicknames.
+    pub weight: f32,
+    /// The spelling of the name the address was generated from, if it is not
+    /// the name as given: a nickname (`bob`) or another transliteration
+    /// (`mueller`).
+    pub name_variant: Option<String>,
+}
+
+impl PatternCandidate {
+    /// The confidence points the template's prior weight is worth.
+    pub(crate) fn confidence_bonus(&self) -> i16 {
+        (self.weight.clamp(0.0, 1.0) * MAX_PATTERN_BONUS).round() as i16
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NamePart {
+    First,
+    Middle,
+    Last,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Name {
+        part: NamePart,
+        initial: bool,
+        max_chars: Option<usize>,
+    },
+}
+
+/// Parses a template such as `{f}.{last}` into its segments.
+fn parse_template(template: &str) -> Result<Vec<Segment>> {
+    let invalid = |reason: String| AppError::Config(format!("'{}': {}", template, reason));
+    let mut segments = Vec::new();
+    let mut rest = template;
+    while !rest.is_empty() {
+        if let Some(after_brace) = rest.strip_prefix('{') {
+            let (placeholder, remainder) = after_brace
+                .split_once('}')
+                .ok_or_else(|| invalid("unclosed '{'".to_string()))?;
+            let (name, max_chars) = match placeholder.split_once(':') {
+                Some((name, length)) => {
+                    let length = length
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|length| *length > 0)
+                        .ok_or_else(|| invalid(format!("invalid length in {{{}}}", placeholder)))?;
+                    (name, Some(length))
+                }
+                None => (placeholder, None),
+            };
+            let (part, initial) = match name {
+                "first" => (NamePart::First, false),
+                "middle" => (NamePart::Middle, false),
+                "last" => (NamePart::Last, false),
+                "f" => (NamePart::First, true),
+                "m" => (NamePart::Middle, true),
+                "l" => (NamePart::Last, true),
+                other => return Err(invalid(format!("unknown placeholder {{{}}}", other))),
+            };
+            segments.push(Segment::Name {
+                part,
+                initial,
+                max_chars,
+            });
+            rest = remainder;
+        } else {
+            let end = rest.find('{').unwrap_or(rest.len());
+            let literal = &rest[..end];
+            if let Some(c) = literal
+                .chars()
+                .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '+')))
+            {
+                return Err(invalid(format!("'{}' is not allowed in a local part", c)));
+            }
+            segments.push(Segment::Literal(literal.to_lowercase()));
+            rest = &rest[end..];
+        }
+    }
+    if !segments
+        .iter()
+        .any(|segment| matches!(segment, Segment::Name { .. }))
+    {
+        return Err(invalid("no name placeholder".to_string()));
+    }
+    Ok(segments)
 }
 
-/// Generates a list of common email address patterns for a given name and domain.
+/// Checks that `template` is a valid pattern template.
+///
+/// # Returns
+/// * `Ok(())` if the template can be used.
+/// * `Err(AppError::Config)` describing the problem otherwise.
+pub(crate) fn validate_template(template: &str) -> Result<()> {
+    parse_template(template).map(|_| ())
+}
+
+/// The sanitized name parts templates are rendered with.
+struct NameParts<'a> {
+    first: &'a str,
+    middle: Option<&'a str>,
+    last: &'a str,
+}
+
+impl NameParts<'_> {
+    /// Renders `segments` into a local part, or `None` if the template uses a
+    /// part this name does not have.
+    fn render(&self, segments: &[Segment]) -> Option<String> {
+        let mut local_part = String::new();
+        for segment in segments {
+            match segment {
+                Segment::Literal(literal) => local_part.push_str(literal),
+                Segment::Name {
+                    part,
+                    initial,
+                    max_chars,
+                } => {
+                    let value = match part {
+                        NamePart::First => self.first,
+                        NamePart::Middle => self.middle?,
+                        NamePart::Last => self.last,
+                    };
+                    if value.is_empty() {
+                        return None;
+                    }
+                    let length = if *initial { Some(1) } else { *max_chars };
+                    match length {
+                        Some(length) => local_part.extend(value.chars().take(length)),
+                        None => local_part.push_str(value),
+                    }
+                }
+            }
+        }
+        Some(local_part)
+    }
+}
+
+/// One spelling of a contact's name to generate candidates from.
+struct NameSpelling<'a> {
+    parts: NameParts<'a>,
+    /// The name parts that differ from the name as given, space-separated.
+    variant: Option<String>,
+    /// Whether the first name is a nickname of the given one.
+    is_nickname: bool,
+}
+
+/// A pattern template parsed once, for rendering it for many names.
+#[derive(Debug, Clone)]
+pub(crate) struct CompiledTemplate {
+    segments: Vec<Segment>,
+}
+
+impl CompiledTemplate {
+    /// Parses `template`.
+    ///
+    /// # Returns
+    /// * `Ok(CompiledTemplate)` if the template is valid.
+    /// * `Err(AppError::Config)` describing the problem otherwise.
+    pub(crate) fn parse(template: &str) -> Result<Self> {
+        parse_template(template).map(|segments| Self { segments })
+    }
+
+    /// Renders the local part for a name whose parts are already sanitized
+    /// with [`normalize_name_part`].
+    ///
+    /// # Returns
+    /// * `Some(String)` with the local part.
+    /// * `None` if the template uses a part the name does not have.
+    pub(crate) fn render(&self, first: &str, middle: Option<&str>, last: &str) -> Option<String> {
+        NameParts {
+            first,
+            middle: middle.filter(|middle| !middle.is_empty()),
+            last,
+        }
+        .render(&self.segments)
+    }
+}
+
+/// Renders the local part `template` produces for a name.
+///
+/// # Returns
+/// * `Some(String)` with the local part.
+/// * `None` if the template is invalid or uses a part the name does not have.
+pub(crate) fn render_template(
+    template: &str,
+    first_name: &str,
+    middle_name: Option<&str>,
+    last_name: &str,
+) -> Option<String> {
+    let template = CompiledTemplate::parse(template).ok()?;
+    let middle = middle_name.map(normalize_name_part);
+    template.render(
+        &normalize_name_part(first_name),
+        middle.as_deref(),
+        &normalize_name_part(last_name),
+    )
+}
+
+/// Generates candidate addresses for a name and domain from the configured
+/// pattern templates.
 ///
 /// # Arguments
 /// * `first_name` - The contact's first name.
-/// * `last_name` - The contact's last name.
+/// * `middle_name` - The contact's middle names or initials, if known. Only
+///   templates using `{middle}` or `{m}` render them.
+/// * `last_name` - The contact's last name. For a surname with particles
+///   (`van Beethoven`) the surname without them is tried as well.
 /// * `domain` - The company domain name (e.g., "example.com").
+/// * `config` - The configuration providing the templates and the email
+///   validation regex.
 ///
 /// # Returns
-/// * `Vec<String>` containing potential email patterns. Returns an empty vector if
-///   names or domain are empty or invalid.
-pub(crate) fn generate_email_patterns(
+/// * `Vec<PatternCandidate>` ordered by descending prior weight, without
+///   duplicate addresses (an address produced by several templates keeps the
+///   highest weight). Empty if names or domain are empty or invalid.
+pub(crate) fn generate_pattern_candidates(
     first_name: &str,
+    middle_name: Option<&str>,
     last_name: &str,
     domain: &str,
-) -> Vec<String> {
+    config: &Config,
+) -> Vec<PatternCandidate> {
     tracing::debug!(
         "Generating patterns for {} {} @ {}",
         first_name,
@@ -40,69 +265,158 @@ pub(crate) fn generate_email_patterns(
         return Vec::new();
     }
 
-    let first = sanitize_name_part(first_name);
-    let last = sanitize_name_part(last_name);
+    let first_variants = name_variants(first_name);
+    let mut last_variants = name_variants(last_name);
+    if let Some(core) = surname_core(last_name) {
+        for variant in name_variants(core) {
+            if !last_variants.contains(&variant) {
+                last_variants.push(variant);
+            }
+        }
+    }
+    let middle = middle_name
+        .map(normalize_name_part)
+        .filter(|middle| !middle.is_empty());
 
-    if first.is_empty() || last.is_empty() {
+    if first_variants.is_empty() || last_variants.is_empty() {
         tracing::warn!(
-            "Cannot generate patterns after sanitizing names: '{} {} @ {}'",
-            first,
-            last,
+            "Cannot generate patterns after normalizing names: '{} {} @ {}'",
+            first_name,
+            last_name,
             domain
         );
         return Vec::new();
     }
 
-    let first_initial = first.chars().next().unwrap_or_default();
-    let last_initial = last.chars().next().unwrap_or_default();
-
-    // Use a HashSet to automatically handle duplicates
-    let mut patterns = HashSet::new();
-
-    patterns.insert(format!("{}@{}", first, domain)); // john@domain.com
-    patterns.insert(format!("{}.{}@{}", first, last, domain)); // john.doe@domain.com
-    patterns.insert(format!("{}{}", first, last)); // johndoe@domain.com - Base part only
-    patterns.insert(format!("{}.{}@{}", last, first, domain)); // doe.john@domain.com
-    patterns.insert(format!("{}{}", last, first)); // doejohn@domain.com - Base part only
-    patterns.insert(format!("{}{}", first_initial, last)); // jdoe@domain.com - Base part only
-    patterns.insert(format!("{}.{}@{}", first_initial, last, domain)); // j.doe@domain.com
-    patterns.insert(format!("{}{}", first, last_initial)); // johnd@domain.com - Base part only
-    patterns.insert(format!("{}.{}@{}", first, last_initial, domain)); // john.d@domain.com
-    patterns.insert(format!("{}_{}@{}", first, last, domain)); // john_doe@domain.com
-    patterns.insert(format!("{}-{}@{}", first, last, domain)); // john-doe@domain.com
-    patterns.insert(format!("{}_{}@{}", last, first, domain)); // doe_john@domain.com
-    patterns.insert(format!("{}-{}@{}", last, first, domain)); // doe-john@domain.com
+    // The given first name in every spelling, then the most common nicknames
+    // at a lower weight.
+    let mut nicknames = alternative_first_names(&first_variants[0], &config.nicknames);
+    nicknames.truncate(MAX_NICKNAMES);
+    let first_names: Vec<(&String, bool)> = first_variants
+        .iter()
+        .map(|first| (first, false))
+        .chain(nicknames.iter().map(|nickname| (nickname, true)))
+        .collect();
 
-    if first.len() >= 3 {
-        patterns.insert(format!("{}{}", &first[0..3], last));
-    }
-    if last.len() >= 3 {
-        patterns.insert(format!("{}{}", first, &last[0..3]));
+    // Every spelling of the first name with every spelling of the last name;
+    // nicknames only with the last name as given.
+    let mut names: Vec<NameSpelling> = Vec::new();
+    for &(first, is_nickname) in &first_names {
+        let lasts = if is_nickname {
+            &last_variants[..1]
+        } else {
+            &last_variants[..]
+        };
+        for last in lasts {
+            let mut differing = Vec::new();
+            if first != &first_variants[0] {
+                differing.push(first.as_str());
+            }
+            if last != &last_variants[0] {
+                differing.push(last.as_str());
+            }
+            names.push(NameSpelling {
+                parts: NameParts {
+                    first,
+                    middle: middle.as_deref(),
+                    last,
+                },
+                variant: (!differing.is_empty()).then(|| differing.join(" ")),
+                is_nickname,
+            });
+        }
     }
 
-    let final_patterns: Vec<String> = patterns
-        .iter()
-        .map(|p| {
-            if !p.contains('@') {
-                format!("{}@{}", p, domain)
-            } else {
-                p.clone()
+    let mut candidates: Vec<PatternCandidate> = Vec::new();
+    let mut index_by_email: HashMap<String, usize> = HashMap::new();
+    for template in &config.pattern_templates {
+        let segments = match parse_template(&template.template) {
+            Ok(segments) => segments,
+            Err(e) => {
+                tracing::warn!("Skipping pattern template {}", e);
+                continue;
             }
-        })
-        .filter(|p| CONFIG.email_regex.is_match(p))
-        .collect();
+        };
+        // A nickname is written out in full or not at all: `bsmith` for
+        // Robert Smith is no more likely than any other initial.
+        let spells_out_first = segments.iter().any(|segment| {
+            matches!(
+                segment,
+                Segment::Name {
+                    part: NamePart::First,
+                    initial: false,
+                    max_chars: None,
+                }
+            )
+        });
+        for name in &names {
+            if name.is_nickname && !spells_out_first {
+                continue;
+            }
+            let Some(local_part) = name.parts.render(&segments) else {
+                continue;
+            };
+            let email = format!("{}@{}", local_part, domain);
+            if !config.email_regex.is_match(&email) {
+                continue;
+            }
+            let candidate = PatternCandidate {
+                email,
+                template: template.template.clone(),
+                weight: if name.is_nickname {
+                    template.weight * config.nickname_weight
+                } else {
+                    template.weight
+                },
+                name_variant: name.variant.clone(),
+            };
+            match index_by_email.get(&candidate.email) {
+                Some(&index) => {
+                    if candidate.weight > candidates[index].weight {
+                        candidates[index] = candidate;
+                    }
+                }
+                None => {
+                    index_by_email.insert(candidate.email.clone(), candidates.len());
+                    candidates.push(candidate);
+                }
+            }
+        }
+    }
+    candidates.sort_by(|a, b| b.weight.total_cmp(&a.weight));
 
-    tracing::debug!("Generated {} unique valid patterns.", final_patterns.len());
-    final_patterns
+    tracing::debug!("Generated {} unique valid patterns.", candidates.len());
+    candidates
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::PatternTemplate;
+
+    fn generate_email_patterns(
+        first_name: &str,
+        last_name: &str,
+        domain: &str,
+        config: &Config,
+    ) -> Vec<String> {
+        generate_pattern_candidates(first_name, None, last_name, domain, config)
+            .into_iter()
+            .map(|candidate| candidate.email)
+            .collect()
+    }
+
+    fn pattern(template: &str, weight: f32) -> PatternTemplate {
+        PatternTemplate {
+            template: template.to_string(),
+            weight,
+        }
+    }
 
     #[test]
     fn test_generate_patterns_basic() {
-        let patterns = generate_email_patterns("John", "Doe", "example.com");
+        let config = Config::default();
+        let patterns = generate_email_patterns("John", "Doe", "example.com", &config);
         assert!(!patterns.is_empty());
         assert!(patterns.contains(&"john.doe@example.com".to_string()));
         assert!(patterns.contains(&"jdoe@example.com".to_string()));
@@ -114,25 +428,30 @@ mod tests {
 
     #[test]
     fn test_generate_patterns_with_spaces() {
-        let patterns = generate_email_patterns(" John ", " Van Der Beek ", "test.co.uk");
+        let config = Config::default();
+        let patterns = generate_email_patterns(" John ", " Van Der Beek ", "test.co.uk", &config);
         assert!(patterns.contains(&"john.vanderbeek@test.co.uk".to_string()));
         assert!(patterns.contains(&"jvanderbeek@test.co.uk".to_string()));
         assert!(patterns.contains(&"johnv@test.co.uk".to_string())); // From john.v@... pattern
+        // The surname without its particles.
+        assert!(patterns.contains(&"john.beek@test.co.uk".to_string()));
     }
 
     #[test]
     fn test_generate_patterns_empty_input() {
-        assert!(generate_email_patterns("", "Doe", "example.com").is_empty());
-        assert!(generate_email_patterns("John", "", "example.com").is_empty());
-        assert!(generate_email_patterns("John", "Doe", "").is_empty());
-        assert!(generate_email_patterns("John", "Doe", "nodot").is_empty()); // Invalid domain
-        assert!(generate_email_patterns(" ", "Doe", "example.com").is_empty()); // Sanitized name becomes empty
+        let config = Config::default();
+        assert!(generate_email_patterns("", "Doe", "example.com", &config).is_empty());
+        assert!(generate_email_patterns("John", "", "example.com", &config).is_empty());
+        assert!(generate_email_patterns("John", "Doe", "", &config).is_empty());
+        assert!(generate_email_patterns("John", "Doe", "nodot", &config).is_empty()); // Invalid domain
+        assert!(generate_email_patterns(" ", "Doe", "example.com", &config).is_empty()); // Sanitized name becomes empty
     }
 
     #[test]
     fn test_generate_patterns_duplicates() {
+        let config = Config::default();
         // Example: If first = "test" and last = "test"
-        let patterns = generate_email_patterns("Test", "Test", "test.com");
+        let patterns = generate_email_patterns("Test", "Test", "test.com", &config);
         let count_test_test = patterns
             .iter()
             .filter(|&p| p == "test.test@test.com")
@@ -141,4 +460,129 @@ mod tests {
         let count_ttest = patterns.iter().filter(|&p| p == "ttest@test.com").count();
         assert_eq!(count_ttest, 1, "Duplicate patterns should be removed");
     }
+
+    #[test]
+    fn test_custom_templates_and_weights() {
+        let config = Config {
+            pattern_templates: vec![
+                pattern("{last}{f}", 0.3),
+                pattern("{first}.{last}2", 0.2),
+                pattern("{f}.{m}.{last}", 0.9),
+                pattern("{first}.{last}", 1.0),
+            ],
+            ..Config::default()
+        };
+        let candidates = generate_pattern_candidates("John", None, "Doe", "example.com", &config);
+        let emails: Vec<&str> = candidates.iter().map(|c| c.email.as_str()).collect();
+        // Templates needing a middle name are skipped; order follows weight.
+        assert_eq!(
+            emails,
+            vec![
+                "john.doe@example.com",
+                "doej@example.com",
+                "john.doe2@example.com"
+            ]
+        );
+        assert_eq!(candidates[0].confidence_bonus(), 5);
+        assert_eq!(candidates[1].confidence_bonus(), 2);
+
+        let candidates =
+            generate_pattern_candidates("John", Some("Q."), "Doe", "example.com", &config);
+        assert_eq!(candidates[1].email, "j.q.doe@example.com");
+
+        assert!(validate_template("{first:3}{l}").is_ok());
+        assert!(validate_template("{first}@{last}").is_err());
+        assert!(validate_template("{nickname}").is_err());
+        assert!(validate_template("{first").is_err());
+        assert!(validate_template("admin").is_err());
+    }
+
+    #[test]
+    fn test_generate_patterns_with_middle_initial() {
+        let config = Config::default();
+        let candidates =
+            generate_pattern_candidates("John", Some("Quincy"), "Public", "example.com", &config);
+        let emails: Vec<&str> = candidates.iter().map(|c| c.email.as_str()).collect();
+        assert!(emails.contains(&"john.q.public@example.com"));
+        assert!(emails.contains(&"jqpublic@example.com"));
+        assert!(emails.contains(&"john.quincy.public@example.com"));
+        // Without a middle name the middle templates are skipped.
+        let patterns = generate_email_patterns("John", "Public", "example.com", &config);
+        assert!(
+            !patterns
+                .iter()
+                .any(|p| p.starts_with("jqpublic") || p.contains(".q."))
+        );
+    }
+
+    #[test]
+    fn test_generate_patterns_for_name_variants() {
+        let config = Config::default();
+        let patterns = generate_email_patterns("José", "Müller", "example.de", &config);
+        assert!(patterns.contains(&"jose.muller@example.de".to_string()));
+        assert!(patterns.contains(&"jose.mueller@example.de".to_string()));
+        assert!(patterns.iter().all(|p| p.is_ascii()));
+
+        let patterns = generate_email_patterns("Anne", "Smith-Jones", "example.com", &config);
+        assert!(patterns.contains(&"anne.smithjones@example.com".to_string()));
+        assert!(patterns.contains(&"anne.smith-jones@example.com".to_string()));
+
+        let patterns = generate_email_patterns("Sean", "O'Brien", "example.ie", &config);
+        assert!(patterns.contains(&"sobrien@example.ie".to_string()));
+    }
+
+    #[test]
+    fn test_generate_patterns_for_nicknames() {
+        let config = Config::default();
+        let candidates =
+            generate_pattern_candidates("Robert", None, "Smith", "example.com", &config);
+        let canonical = candidates
+            .iter()
+            .find(|c| c.email == "robert.smith@example.com")
+            .unwrap();
+        let nickname = candidates
+            .iter()
+            .find(|c| c.email == "bob.smith@example.com")
+            .unwrap();
+        assert_eq!(canonical.name_variant, None);
+        assert_eq!(nickname.name_variant.as_deref(), Some("bob"));
+        assert!(nickname.weight < canonical.weight);
+        assert_eq!(nickname.template, canonical.template);
+
+        // Only the most common nicknames, spelled out in full, below the
+        // confidence needed for SMTP verification.
+        let nicknames: Vec<&PatternCandidate> = candidates
+            .iter()
+            .filter(|c| c.name_variant.is_some())
+            .collect();
+        assert!(nicknames.iter().all(|c| c.confidence_bonus() <= 1));
+        assert!(!candidates.iter().any(|c| c.email.starts_with("bobby")));
+        assert!(!candidates.iter().any(|c| c.email == "bsmith@example.com"));
+
+        let candidates = generate_pattern_candidates(
+            "Elizabeth",
+            Some("Anne"),
+            "Müller-Schmidt",
+            "example.de",
+            &config,
+        );
+        let nickname_count = candidates
+            .iter()
+            .filter(|c| {
+                c.name_variant
+                    .as_deref()
+                    .is_some_and(|v| v.contains("liz") || v.contains("beth"))
+            })
+            .count();
+        assert!(nickname_count <= MAX_NICKNAMES * config.pattern_templates.len());
+        assert!(candidates.len() <= 100, "{} candidates", candidates.len());
+
+        let candidates =
+            generate_pattern_candidates("Jürgen", None, "Müller", "example.de", &config);
+        let transliterated = candidates
+            .iter()
+            .find(|c| c.email == "jurgen.mueller@example.de")
+            .unwrap();
+        assert_eq!(transliterated.name_variant.as_deref(), Some("mueller"));
+    }
 }
@@ -1,20 +1,28 @@
 //! Functions for processing individual contact records.
 
-use crate::config::CONFIG;
+use crate::deferred::DeferredVerifications;
 use crate::domain::{get_domain_from_url, normalize_url};
-use crate::models::{Contact, EmailResult, ProcessingResult, ValidatedContact};
+use crate::models::{
+    Contact, EmailResult, ParsedName, ProcessingResult, ValidatedContact, VerificationStatus,
+};
+use crate::name_parser::parse_full_name;
 use crate::sleuth::EmailSleuth;
-use std::sync::Arc; // For Arc<EmailSleuth>
 
 /// Processes a single contact record to find and verify an email address.
 ///
 /// # Arguments
-/// * `sleuth` - An Arc-wrapped `EmailSleuth` instance containing shared clients.
+/// * `sleuth` - The `EmailSleuth` instance containing shared clients and configuration.
 /// * `record` - The input `Contact` record.
+/// * `deferred` - Queue for greylisted addresses to re-verify later, if the
+///   caller completes deferred verifications at the end of its batch.
 ///
 /// # Returns
 /// * `ProcessingResult` containing the original input and the discovery results or errors.
-pub(crate) async fn process_record(sleuth: Arc<EmailSleuth>, record: Contact) -> ProcessingResult {
+pub(crate) async fn process_record(
+    sleuth: &EmailSleuth,
+    record: Contact,
+    deferred: Option<&DeferredVerifications>,
+) -> ProcessingResult {
     let record_id = record
         .full_name
         .as_deref()
@@ -28,13 +36,12 @@ pub(crate) async fn process_record(sleuth: Arc<EmailSleuth>, record: Contact) ->
     );
     tracing::info!(target: "process_record_task", "[{}] Starting processing.", task_id);
 
-    let mut first_name = record
-        .first_name
-        .as_deref()
-        .unwrap_or("")
-        .trim()
-        .to_string();
-    let mut last_name = record.last_name.as_deref().unwrap_or("").trim().to_string();
+    let ContactNames {
+        first_name,
+        middle_name,
+        last_name,
+        parsed_name,
+    } = contact_names(&record, &task_id);
     let original_full_name = record.full_name.as_deref().unwrap_or("").trim().to_string();
     let domain_input_str = record
         .domain
@@ -45,35 +52,10 @@ pub(crate) async fn process_record(sleuth: Arc<EmailSleuth>, record: Contact) ->
         .to_string();
 
     tracing::debug!(target: "process_record_task",
-        "[{}] Input: FN='{}', LN='{}', Full(Original)='{}', DomainInput='{}'",
-        task_id, first_name, last_name, original_full_name, domain_input_str
+        "[{}] Names: FN='{}', MN='{}', LN='{}', Full(Original)='{}', DomainInput='{}'",
+        task_id, first_name, middle_name.as_deref().unwrap_or(""), last_name, original_full_name, domain_input_str
     );
 
-    if (first_name.is_empty() || last_name.is_empty()) && !original_full_name.is_empty() {
-        let name_parts: Vec<&str> = original_full_name.split_whitespace().collect();
-        if name_parts.len() >= 2 {
-            if first_name.is_empty() {
-                first_name = name_parts[0].to_string();
-            }
-            if last_name.is_empty() {
-                last_name = name_parts.last().unwrap_or(&"").to_string();
-            }
-            tracing::debug!(target: "process_record_task", "[{}] Derived names: First='{}', Last='{}'", task_id, first_name, last_name);
-        } else if name_parts.len() == 1 {
-            if first_name.is_empty() && last_name.is_empty() {
-                first_name = name_parts[0].to_string();
-                last_name = name_parts[0].to_string();
-                tracing::debug!(target: "process_record_task", "[{}] Derived single name part for both: '{}'", task_id, name_parts[0]);
-            } else if first_name.is_empty() {
-                first_name = name_parts[0].to_string();
-                tracing::debug!(target: "process_record_task", "[{}] Derived single first name part from full: '{}'", task_id, name_parts[0]);
-            } else {
-                last_name = name_parts[0].to_string();
-                tracing::debug!(target: "process_record_task", "[{}] Derived single last name part from full: '{}'", task_id, name_parts[0]);
-            }
-        }
-    }
-
     let mut missing_parts = Vec::new();
     if first_name.is_empty() {
         missing_parts.push("first name");
@@ -93,12 +75,14 @@ pub(crate) async fn process_record(sleuth: Arc<EmailSleuth>, record: Contact) ->
             email_discovery_results: None,
             email: None,
             email_confidence: None,
+            email_verification: None,
             email_verification_method: None,
             email_alternatives: vec![],
             email_finding_skipped: true,
             email_finding_reason: Some(reason),
             email_verification_failed: false,
             email_finding_error: None,
+            parsed_name,
         };
     }
 
@@ -115,12 +99,14 @@ pub(crate) async fn process_record(sleuth: Arc<EmailSleuth>, record: Contact) ->
                 email_discovery_results: None,
                 email: None,
                 email_confidence: None,
+                email_verification: None,
                 email_verification_method: None,
                 email_alternatives: vec![],
                 email_finding_skipped: true,
                 email_finding_reason: Some(reason),
                 email_verification_failed: false,
                 email_finding_error: None,
+                parsed_name,
             };
         }
     };
@@ -138,12 +124,14 @@ pub(crate) async fn process_record(sleuth: Arc<EmailSleuth>, record: Contact) ->
                 email_discovery_results: None,
                 email: None,
                 email_confidence: None,
+                email_verification: None,
                 email_verification_method: None,
                 email_alternatives: vec![],
                 email_finding_skipped: true,
                 email_finding_reason: Some(reason),
                 email_verification_failed: false,
                 email_finding_error: None,
+                parsed_name,
             };
         }
     };
@@ -157,6 +145,7 @@ pub(crate) async fn process_record(sleuth: Arc<EmailSleuth>, record: Contact) ->
 
     let validated_contact = ValidatedContact {
         first_name,
+        middle_name,
         last_name,
         full_name: final_full_name,
         website_url,
@@ -165,44 +154,24 @@ pub(crate) async fn process_record(sleuth: Arc<EmailSleuth>, record: Contact) ->
     };
 
     let find_result: std::result::Result<EmailResult, crate::error::AppError> =
-        sleuth.find_email(&validated_contact).await;
+        sleuth.find_email_with(&validated_contact, deferred).await;
 
     match find_result {
         Ok(results) => {
-            let mut final_record = ProcessingResult {
-                contact_input: record,
-                email_discovery_results: Some(results.clone()),
-                email: results.most_likely_email.clone(),
-                email_confidence: results
-                    .most_likely_email
-                    .as_ref()
-                    .map(|_| results.confidence_score),
-                email_verification_method: Some(results.methods_used.join(", ")),
-                email_alternatives: results
-                    .found_emails
-                    .iter()
-                    .filter(|e| Some(&e.email) != results.most_likely_email.as_ref())
-                    .take(CONFIG.max_alternatives)
-                    .map(|e| e.email.clone())
-                    .collect(),
-                email_finding_skipped: false,
-                email_finding_reason: None,
-                email_verification_failed: results.most_likely_email.is_none()
-                    && !results.found_emails.is_empty(),
-                email_finding_error: None,
-            };
+            let final_record = summarize_results(
+                record,
+                parsed_name,
+                results,
+                sleuth.config().max_alternatives,
+            );
 
-            if final_record.email.is_some() {
+            if let Some(email) = &final_record.email {
                 tracing::info!(target: "process_record_task",
                     "[{}] ✓ Found likely email: {} (Confidence: {}/10)",
-                    task_id, final_record.email.as_ref().unwrap(), final_record.email_confidence.unwrap()
+                    task_id, email, final_record.email_confidence.unwrap_or_default()
                 );
-                final_record.email_verification_failed = false;
             } else {
                 tracing::info!(target: "process_record_task", "[{}] ✗ No high-confidence email found.", task_id);
-                if !results.found_emails.is_empty() {
-                    final_record.email_verification_failed = true;
-                }
             }
             tracing::info!(target: "process_record_task", "[{}] Finished processing.", task_id);
             final_record
@@ -216,13 +185,193 @@ pub(crate) async fn process_record(sleuth: Arc<EmailSleuth>, record: Contact) ->
                 email_discovery_results: None,
                 email: None,
                 email_confidence: None,
+                email_verification: None,
                 email_verification_method: None,
                 email_alternatives: vec![],
                 email_finding_skipped: false,
                 email_finding_reason: None,
                 email_verification_failed: false,
                 email_finding_error: Some(format!("Core processing error: {}", e)),
+                parsed_name,
+            }
+        }
+    }
+}
+
+/// The name parts a contact is searched with.
+struct ContactNames {
+    first_name: String,
+    middle_name: Option<String>,
+    last_name: String,
+    /// The components parsed from the record's full name, if it has one.
+    parsed_name: Option<ParsedName>,
+}
+
+/// Takes the name parts from the record's fields, filling the missing ones
+/// from its parsed `full_name`.
+///
+/// A full name of a single word is only used as the first name: it does not
+/// say whether it is a first or a last name, so it never becomes the last
+/// name as well.
+fn contact_names(record: &Contact, task_id: &str) -> ContactNames {
+    let mut first_name = record
+        .first_name
+        .as_deref()
+        .unwrap_or("")
+        .trim()
+        .to_string();
+    let mut middle_name = record
+        .middle_name
+        .as_deref()
+        .map(str::trim)
+        .filter(|middle| !middle.is_empty())
+        .map(str::to_string);
+    let mut last_name = record.last_name.as_deref().unwrap_or("").trim().to_string();
+
+    let parsed_name = record
+        .full_name
+        .as_deref()
+        .map(str::trim)
+        .filter(|full_name| !full_name.is_empty())
+        .map(parse_full_name);
+    if let Some(parsed) = &parsed_name {
+        tracing::debug!(target: "process_record_task", "[{}] Parsed full name: {:?}", task_id, parsed);
+        if middle_name.is_none() {
+            middle_name = parsed.middle.clone();
+        }
+        match &parsed.last {
+            Some(last) => {
+                if first_name.is_empty() {
+                    first_name = parsed.first.clone();
+                }
+                if last_name.is_empty() {
+                    last_name = last.clone();
+                }
+                tracing::debug!(target: "process_record_task", "[{}] Derived names: First='{}', Last='{}'", task_id, first_name, last_name);
+            }
+            None if parsed.first.is_empty() => {}
+            None => {
+                if first_name.is_empty() {
+                    first_name = parsed.first.clone();
+                    tracing::debug!(target: "process_record_task", "[{}] Derived single first name part from full: '{}'", task_id, parsed.first);
+                }
             }
         }
     }
+
+    ContactNames {
+        first_name,
+        middle_name,
+        last_name,
+        parsed_name,
+    }
+}
+
+/// Builds the output record for a contact whose email search completed,
+/// filling the convenience fields from the discovery results.
+///
+/// # Arguments
+/// * `contact_input` - The original input record.
+/// * `parsed_name` - The components parsed from the record's full name.
+/// * `results` - The findings returned by the email search.
+/// * `max_alternatives` - Maximum number of alternative emails to list.
+pub(crate) fn summarize_results(
+    contact_input: Contact,
+    parsed_name: Option<ParsedName>,
+    results: EmailResult,
+    max_alternatives: usize,
+) -> ProcessingResult {
+    ProcessingResult {
+        contact_input,
+        email: results.most_likely_email.clone(),
+        email_confidence: results
+            .most_likely_email
+            .as_ref()
+            .map(|_| results.confidence_score),
+        email_verification: results.most_likely_email.as_ref().and_then(|email| {
+            results
+                .found_emails
+                .iter()
+                .find(|e| &e.email == email)
+                .map(|e| e.verification)
+        }),
+        email_verification_method: Some(results.methods_used.join(", ")),
+        email_alternatives: results
+            .found_emails
+            .iter()
+            .filter(|e| {
+                Some(&e.email) != results.most_likely_email.as_ref()
+                    && e.verification != VerificationStatus::Invalid
+            })
+            .take(max_alternatives)
+            .map(|e| e.email.clone())
+            .collect(),
+        email_finding_skipped: false,
+        email_finding_reason: results
+            .accepts_no_email
+            .then(|| "Domain does not accept email (null MX)".to_string()),
+        email_verification_failed: results.most_likely_email.is_none()
+            && !results.found_emails.is_empty(),
+        email_finding_error: None,
+        parsed_name,
+        email_discovery_results: Some(results),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(fields: serde_json::Value) -> Contact {
+        serde_json::from_value(fields).unwrap()
+    }
+
+    #[test]
+    fn test_contact_names_take_middle_name_from_field_or_full_name() {
+        let names = contact_names(
+            &record(serde_json::json!({
+                "first_name": "John",
+                "middle_name": " Quincy ",
+                "last_name": "Public",
+            })),
+            "test",
+        );
+        assert_eq!(names.middle_name.as_deref(), Some("Quincy"));
+        assert!(names.parsed_name.is_none());
+
+        let names = contact_names(
+            &record(serde_json::json!({ "full_name": "Dr. John Q. Public Jr." })),
+            "test",
+        );
+        assert_eq!(names.first_name, "John");
+        assert_eq!(names.middle_name.as_deref(), Some("Q"));
+        assert_eq!(names.last_name, "Public");
+
+        // The middle_name field wins over the one in full_name.
+        let names = contact_names(
+            &record(serde_json::json!({
+                "middle_name": "Quincy",
+                "full_name": "John Q. Public",
+            })),
+            "test",
+        );
+        assert_eq!(names.middle_name.as_deref(), Some("Quincy"));
+    }
+
+    #[tokio::test]
+    async fn test_process_record_skips_single_word_full_name() {
+        let sleuth = EmailSleuth::builder().build().await.unwrap();
+        let result = process_record(
+            &sleuth,
+            record(serde_json::json!({ "full_name": "Madonna", "domain": "example.com" })),
+            None,
+        )
+        .await;
+        assert!(result.email_finding_skipped);
+        assert_eq!(
+            result.email_finding_reason.as_deref(),
+            Some("Missing last name")
+        );
+        assert_eq!(result.parsed_name.unwrap().first, "Madonna");
+    }
 }
@@ -0,0 +1,451 @@
+//! Pluggable DNS resolution for mail server discovery.
+//!
+//! All lookups made while finding an email go through the [`MailResolver`]
+//! trait. [`TrustDnsResolver`] queries real name servers; [`StaticResolver`]
+//! answers from a zone file, for offline runs or to serve an internal
+//! split-horizon zone while other names still go to the network.
+
+use crate::error::{AppError, Result};
+use futures::future::BoxFuture;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::Arc;
+use trust_dns_resolver::TokioAsyncResolver;
+use trust_dns_resolver::error::{ResolveError, ResolveErrorKind};
+use trust_dns_resolver::proto::op::ResponseCode;
+
+/// An MX record returned by a [`MailResolver`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MxRecord {
+    /// The preference value (lower is more preferred).
+    pub preference: u16,
+    /// The mail exchanger host name.
+    pub exchange: String,
+}
+
+/// Resolves the DNS records needed to find and verify email addresses.
+///
+/// Implementations return `Ok` with an empty list if the name exists but has
+/// no records of the requested type, [`AppError::NxDomain`] if the name does
+/// not exist and [`AppError::DnsTimeout`] if the lookup timed out.
+pub trait MailResolver: Send + Sync + std::fmt::Debug {
+    /// Looks up the MX records of `domain`.
+    fn lookup_mx<'a>(&'a self, domain: &'a str) -> BoxFuture<'a, Result<Vec<MxRecord>>>;
+
+    /// Looks up the A and AAAA records of `host`.
+    fn lookup_ip<'a>(&'a self, host: &'a str) -> BoxFuture<'a, Result<Vec<IpAddr>>>;
+
+    /// Looks up the TXT records of `name`. Each record's character strings
+    /// are concatenated.
+    fn lookup_txt<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<String>>>;
+}
+
+/// A [`MailResolver`] backed by a trust-dns resolver.
+#[derive(Clone)]
+pub struct TrustDnsResolver {
+    resolver: TokioAsyncResolver,
+}
+
+impl TrustDnsResolver {
+    /// Wraps a configured trust-dns resolver.
+    pub fn new(resolver: TokioAsyncResolver) -> Self {
+        Self { resolver }
+    }
+}
+
+impl std::fmt::Debug for TrustDnsResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TrustDnsResolver").finish_non_exhaustive()
+    }
+}
+
+/// Maps a trust-dns error onto the [`MailResolver`] conventions: a name
+/// without records of the type is an empty answer.
+fn no_records_or_error<T>(error: ResolveError, name: &str) -> Result<Vec<T>> {
+    match error.kind() {
+        ResolveErrorKind::NoRecordsFound { response_code, .. }
+            if *response_code == ResponseCode::NXDomain =>
+        {
+            Err(AppError::NxDomain(name.to_string()))
+        }
+        ResolveErrorKind::NoRecordsFound { .. } => Ok(Vec::new()),
+        ResolveErrorKind::Timeout => Err(AppError::DnsTimeout(name.to_string())),
+        _ => Err(AppError::Dns(error)),
+    }
+}
+
+impl MailResolver for TrustDnsResolver {
+    fn lookup_mx<'a>(&'a self, domain: &'a str) -> BoxFuture<'a, Result<Vec<MxRecord>>> {
+        Box::pin(async move {
+            match self.resolver.mx_lookup(domain).await {
+                Ok(response) => Ok(response
+                    .iter()
+                    .map(|mx| MxRecord {
+                        preference: mx.preference(),
+                        exchange: mx.exchange().to_utf8(),
+                    })
+                    .collect()),
+                Err(e) => no_records_or_error(e, domain),
+            }
+        })
+    }
+
+    fn lookup_ip<'a>(&'a self, host: &'a str) -> BoxFuture<'a, Result<Vec<IpAddr>>> {
+        Box::pin(async move {
+            match self.resolver.lookup_ip(host).await {
+                Ok(response) => Ok(response.iter().collect()),
+                Err(e) => no_records_or_error(e, host),
+            }
+        })
+    }
+
+    fn lookup_txt<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<String>>> {
+        Box::pin(async move {
+            match self.resolver.txt_lookup(name).await {
+                Ok(response) => Ok(response
+                    .iter()
+                    .map(|txt| {
+                        txt.iter()
+                            .map(|part| String::from_utf8_lossy(part))
+                            .collect::<String>()
+                    })
+                    .collect()),
+                Err(e) => no_records_or_error(e, name),
+            }
+        })
+    }
+}
+
+/// The [`MailResolver`] method a [`StaticResolver`] forwards unknown names to.
+type FallbackLookup<'a, T> = fn(&'a dyn MailResolver, &'a str) -> BoxFuture<'a, Result<Vec<T>>>;
+
+/// The records of one name in a [`StaticResolver`] zone.
+#[derive(Debug, Clone, Default)]
+struct ZoneEntry {
+    mx: Vec<MxRecord>,
+    addresses: Vec<IpAddr>,
+    txt: Vec<String>,
+}
+
+/// One name in a TOML zone file.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct TomlZoneEntry {
+    #[serde(default)]
+    mx: Vec<String>,
+    #[serde(default)]
+    a: Vec<IpAddr>,
+    #[serde(default)]
+    aaaa: Vec<IpAddr>,
+    #[serde(default)]
+    txt: Vec<String>,
+}
+
+/// A [`MailResolver`] that answers from a fixed zone.
+///
+/// Zones are loaded from a TOML file with one table per name:
+///
+/// ```toml
+/// ["example.com"]
+/// mx = ["10 mx1.example.com", "20 mx2.example.com"]
+/// txt = ["v=spf1 mx -all"]
+///
+/// ["mx1.example.com"]
+/// a = ["192.0.2.25"]
+/// ```
+///
+/// or from a hosts-style file where lines are either `address name...`, as in
+/// `/etc/hosts`, or `name TYPE data` records for the types MX, A, AAAA and TXT:
+///
+/// ```text
+/// example.com      MX   10 mx1.example.com
+/// example.com      TXT  "v=spf1 mx -all"
+/// 192.0.2.25       mx1.example.com
+/// ```
+///
+/// Names missing from the zone do not exist, unless a fallback resolver is
+/// set with [`StaticResolver::with_fallback`].
+#[derive(Debug, Default)]
+pub struct StaticResolver {
+    zone: HashMap<String, ZoneEntry>,
+    fallback: Option<Arc<dyn MailResolver>>,
+}
+
+/// Normalizes a DNS name for zone lookups.
+fn zone_key(name: &str) -> String {
+    name.trim().trim_end_matches('.').to_lowercase()
+}
+
+/// Removes a `#` comment from a hosts-style zone line. A `#` inside quoted
+/// TXT data is part of the record.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Parses MX data of the form `preference exchange`.
+fn parse_mx(data: &str) -> Option<MxRecord> {
+    let mut parts = data.split_whitespace();
+    let preference = parts.next()?.parse().ok()?;
+    let exchange = parts.next()?.to_string();
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(MxRecord {
+        preference,
+        exchange,
+    })
+}
+
+impl StaticResolver {
+    /// Creates a resolver with an empty zone.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads a zone file. Files ending in `.toml` are read as TOML zones,
+    /// anything else as hosts-style zones.
+    ///
+    /// # Returns
+    /// * `Ok(StaticResolver)` answering from the file.
+    /// * `Err(AppError)` if the file cannot be read or contains invalid records.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+        let resolver = if path.extension().is_some_and(|ext| ext == "toml") {
+            Self::from_toml(&content)?
+        } else {
+            Self::from_hosts(&content)?
+        };
+        tracing::info!(
+            "Loaded static DNS zone with {} names from {}",
+            resolver.zone.len(),
+            path.display()
+        );
+        Ok(resolver)
+    }
+
+    /// Parses a TOML zone.
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let entries: HashMap<String, TomlZoneEntry> = toml::from_str(content)
+            .map_err(|e| AppError::Config(format!("Invalid TOML zone file: {}", e)))?;
+        let mut resolver = Self::new();
+        for (name, entry) in entries {
+            let mut mx = Vec::with_capacity(entry.mx.len());
+            for data in &entry.mx {
+                mx.push(parse_mx(data).ok_or_else(|| {
+                    AppError::Config(format!("Invalid MX record for {}: '{}'", name, data))
+                })?);
+            }
+            let zone_entry = resolver.zone.entry(zone_key(&name)).or_default();
+            zone_entry.mx.extend(mx);
+            zone_entry.addresses.extend(entry.a);
+            zone_entry.addresses.extend(entry.aaaa);
+            zone_entry.txt.extend(entry.txt);
+        }
+        Ok(resolver)
+    }
+
+    /// Parses a hosts-style zone. `#` starts a comment, except inside quoted
+    /// TXT data.
+    pub fn from_hosts(content: &str) -> Result<Self> {
+        let mut resolver = Self::new();
+        for (index, raw_line) in content.lines().enumerate() {
+            let line = strip_comment(raw_line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid =
+                || AppError::Config(format!("Invalid zone file line {}: '{}'", index + 1, line));
+
+            let mut tokens = line.split_whitespace();
+            let first = tokens.next().unwrap_or_default();
+            if let Ok(address) = first.parse::<IpAddr>() {
+                let names: Vec<&str> = tokens.collect();
+                if names.is_empty() {
+                    return Err(invalid());
+                }
+                for name in names {
+                    resolver
+                        .zone
+                        .entry(zone_key(name))
+                        .or_default()
+                        .addresses
+                        .push(address);
+                }
+                continue;
+            }
+
+            let record_type = tokens.next().ok_or_else(invalid)?.to_ascii_uppercase();
+            let data = tokens.collect::<Vec<_>>().join(" ");
+            let entry = resolver.zone.entry(zone_key(first)).or_default();
+            match record_type.as_str() {
+                "MX" => entry.mx.push(parse_mx(&data).ok_or_else(invalid)?),
+                "A" | "AAAA" => entry.addresses.push(data.parse().map_err(|_| invalid())?),
+                "TXT" => entry.txt.push(data.trim_matches('"').to_string()),
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(resolver)
+    }
+
+    /// Sends lookups for names missing from the zone to `fallback`.
+    pub fn with_fallback(mut self, fallback: Arc<dyn MailResolver>) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Answers from the zone, or from the fallback resolver for unknown names.
+    fn answer<'a, T: Clone + Send + 'a>(
+        &'a self,
+        name: &'a str,
+        records: fn(&ZoneEntry) -> &Vec<T>,
+        fallback_lookup: FallbackLookup<'a, T>,
+    ) -> BoxFuture<'a, Result<Vec<T>>> {
+        match (self.zone.get(&zone_key(name)), &self.fallback) {
+            (Some(entry), _) => Box::pin(std::future::ready(Ok(records(entry).clone()))),
+            (None, Some(fallback)) => fallback_lookup(fallback.as_ref(), name),
+            (None, None) => Box::pin(std::future::ready(Err(AppError::NxDomain(
+                name.to_string(),
+            )))),
+        }
+    }
+}
+
+impl MailResolver for StaticResolver {
+    fn lookup_mx<'a>(&'a self, domain: &'a str) -> BoxFuture<'a, Result<Vec<MxRecord>>> {
+        self.answer(
+            domain,
+            |entry| &entry.mx,
+            |resolver, name| resolver.lookup_mx(name),
+        )
+    }
+
+    fn lookup_ip<'a>(&'a self, host: &'a str) -> BoxFuture<'a, Result<Vec<IpAddr>>> {
+        if let Ok(address) = host.parse::<IpAddr>() {
+            return Box::pin(std::future::ready(Ok(vec![address])));
+        }
+        self.answer(
+            host,
+            |entry| &entry.addresses,
+            |resolver, name| resolver.lookup_ip(name),
+        )
+    }
+
+    fn lookup_txt<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<String>>> {
+        self.answer(
+            name,
+            |entry| &entry.txt,
+            |resolver, name| resolver.lookup_txt(name),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_static_resolver_from_toml() {
+        let resolver = StaticResolver::from_toml(
+            r#"
+            ["Example.com."]
+            mx = ["10 mx1.example.com", "20 mx2.example.com"]
+            txt = ["v=spf1 mx -all"]
+
+            ["mx1.example.com"]
+            a = ["192.0.2.25"]
+            aaaa = ["2001:db8::25"]
+            "#,
+        )
+        .unwrap();
+
+        let mx = resolver.lookup_mx("example.com").await.unwrap();
+        assert_eq!(mx.len(), 2);
+        assert_eq!(mx[0].exchange, "mx1.example.com");
+        assert_eq!(
+            resolver.lookup_txt("example.com").await.unwrap(),
+            vec!["v=spf1 mx -all"]
+        );
+        assert_eq!(
+            resolver.lookup_ip("mx1.example.com").await.unwrap().len(),
+            2
+        );
+        assert!(
+            resolver
+                .lookup_mx("mx1.example.com")
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(matches!(
+            resolver.lookup_mx("unknown.example").await,
+            Err(AppError::NxDomain(_))
+        ));
+        assert!(
+            StaticResolver::from_toml(
+                r#"["example.com"]
+            mx = ["mx1.example.com"]"#
+            )
+            .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_static_resolver_from_hosts() {
+        let resolver = StaticResolver::from_hosts(
+            "# internal zone\n\
+             example.com  MX   10 mx1.example.com\n\
+             example.com  TXT  \"v=spf1 -all\"\n\
+             _domainkey.example.com  TXT  \"see https://example.com/dkim#keys\"  # key docs\n\
+             192.0.2.25   mx1.example.com mail.example.com  # both names\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            resolver.lookup_mx("example.com").await.unwrap(),
+            vec![MxRecord {
+                preference: 10,
+                exchange: "mx1.example.com".to_string()
+            }]
+        );
+        assert_eq!(
+            resolver.lookup_txt("example.com").await.unwrap(),
+            vec!["v=spf1 -all"]
+        );
+        assert_eq!(
+            resolver.lookup_txt("_domainkey.example.com").await.unwrap(),
+            vec!["see https://example.com/dkim#keys"]
+        );
+        let address: IpAddr = "192.0.2.25".parse().unwrap();
+        assert_eq!(
+            resolver.lookup_ip("mail.example.com").await.unwrap(),
+            vec![address]
+        );
+        assert!(StaticResolver::from_hosts("example.com SRV 0 5 5060 sip").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_static_resolver_fallback() {
+        let internal = StaticResolver::from_hosts("corp.example MX 10 mx.corp.example").unwrap();
+        let public = StaticResolver::from_hosts("example.org MX 5 mx.example.org").unwrap();
+        let resolver = internal.with_fallback(Arc::new(public));
+
+        assert_eq!(resolver.lookup_mx("corp.example").await.unwrap().len(), 1);
+        assert_eq!(
+            resolver.lookup_mx("example.org").await.unwrap()[0].exchange,
+            "mx.example.org"
+        );
+        assert!(resolver.lookup_mx("example.net").await.is_err());
+    }
+}
@@ -1,178 +1,363 @@
 //! Functions for scraping website content to find email addresses.
+//!
+//! A website is crawled breadth-first from its start page. Links to other
+//! pages of the same site are followed when their path or text mentions one
+//! of the configured keywords (team, about, leadership, ...), most relevant
+//! first, until the page or depth limit is reached. Redirects are followed,
+//! and a site's `www` and apex hosts count as the same site.
 
-use crate::config::{CONFIG, get_random_sleep_duration};
+use crate::config::Config;
 use crate::error::Result;
+use once_cell::sync::Lazy;
 use reqwest::Client;
 use scraper::{Html, Selector};
 use std::collections::{HashSet, VecDeque};
 use std::time::Instant;
 use url::Url;
 
-/// Scrapes a website (starting URL + common pages) to find email addresses.
-///
-/// # Arguments
-/// * `http_client` - A shared `reqwest::Client` instance.
-/// * `base_url` - The starting URL of the website to scrape.
-/// * `target_domain` - The primary domain we are interested in emails for (lowercase).
+static EMAIL_LINK_SELECTOR: Lazy<Selector> =
+    Lazy::new(|| Selector::parse("a[href^='mailto:']").unwrap());
+static LINK_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("a[href]").unwrap());
+static BODY_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("body").unwrap());
+
+/// File extensions of links that do not lead to HTML pages.
+const SKIPPED_EXTENSIONS: &[&str] = &[
+    "pdf", "jpg", "jpeg", "png", "gif", "svg", "webp", "ico", "zip", "doc", "docx", "xls", "xlsx",
+    "ppt", "pptx", "mp3", "mp4", "css", "js", "xml", "ics",
+];
+
+/// The outcome of fetching one page.
+enum PageFetch {
+    /// An HTML page, with the URL it was served from after redirects.
+    Html { url: Url, content: String },
+    /// A page that is not HTML.
+    NotHtml,
+    /// The request failed or the server answered with an error.
+    Failed,
+}
+
+/// The host of `url` without a leading `www.`, so that a site's `www` and
+/// apex hosts compare equal.
+fn site_host(url: &Url) -> Option<String> {
+    let host = url.host_str()?.to_lowercase();
+    Some(match host.strip_prefix("www.") {
+        Some(apex) => apex.to_string(),
+        None => host,
+    })
+}
+
+/// The key pages are deduplicated by, so that a page reached over http and
+/// https, or with and without `www`, is fetched once.
+fn page_key(url: &Url) -> String {
+    let mut key = site_host(url).unwrap_or_default();
+    key.push_str(url.path().trim_end_matches('/'));
+    if let Some(query) = url.query() {
+        key.push('?');
+        key.push_str(query);
+    }
+    key
+}
+
+/// Scores a link by the keywords in its path and text. A keyword in the path
+/// counts double.
 ///
 /// # Returns
-/// * `Result<Vec<String>>` containing a list of unique, potentially valid email addresses found.
-pub(crate) async fn scrape_website_for_emails(
-    http_client: &Client,
-    base_url: &Url,
-) -> Result<Vec<String>> {
-    let start_time = Instant::now();
-    tracing::info!(target: "scrape_task", "Starting scrape for: {}", base_url);
+/// * `u32` score, zero if the link mentions no keyword.
+fn score_link(url: &Url, text: &str, keywords: &[String]) -> u32 {
+    let path = url.path().to_lowercase();
+    let text = text.to_lowercase();
+    keywords
+        .iter()
+        .map(|keyword| {
+            2 * u32::from(path.contains(keyword.as_str()))
+                + u32::from(text.contains(keyword.as_str()))
+        })
+        .sum()
+}
 
-    let mut found_emails: HashSet<String> = HashSet::new();
-    let mut processed_urls: HashSet<String> = HashSet::new();
-    let mut urls_to_visit: VecDeque<Url> = VecDeque::new();
-    let mut successful_pages = 0;
-    let mut failed_pages = 0;
+/// Extracts the links on a page that lead to pages of the same site, with the
+/// text of each link.
+///
+/// # Arguments
+/// * `document` - The parsed page.
+/// * `page_url` - The URL the page was served from, for resolving relative links.
+/// * `site_hosts` - The hosts (without `www.`) belonging to the site.
+fn extract_links(
+    document: &Html,
+    page_url: &Url,
+    site_hosts: &HashSet<String>,
+) -> Vec<(Url, String)> {
+    document
+        .select(&LINK_SELECTOR)
+        .filter_map(|element| {
+            let href = element.value().attr("href")?.trim();
+            let mut url = page_url.join(href).ok()?;
+            if !matches!(url.scheme(), "http" | "https")
+                || !site_host(&url).is_some_and(|host| site_hosts.contains(&host))
+            {
+                return None;
+            }
+            let extension = url
+                .path()
+                .rsplit_once('.')
+                .map(|(_, extension)| extension.to_lowercase());
+            if extension.is_some_and(|extension| SKIPPED_EXTENSIONS.contains(&extension.as_str())) {
+                return None;
+            }
+            url.set_fragment(None);
+            let text = element.text().collect::<Vec<_>>().join(" ");
+            Some((url, text))
+        })
+        .collect()
+}
+
+/// Collects the email addresses on a page, from `mailto:` links and from the
+/// page text.
+fn extract_emails(
+    document: &Html,
+    page_url: &Url,
+    config: &Config,
+    found_emails: &mut HashSet<String>,
+) {
+    for element in document.select(&EMAIL_LINK_SELECTOR) {
+        if let Some(href) = element.value().attr("href")
+            && let Some(email_part) = href.strip_prefix("mailto:")
+        {
+            let email = email_part.split('?').next().unwrap_or("").trim();
+            if !email.is_empty() && config.email_regex.is_match(email) {
+                tracing::debug!(target: "scrape_task", "Found via mailto link ({}): {}", page_url, email);
+                found_emails.insert(email.to_lowercase());
+            } else if !email.is_empty() {
+                tracing::warn!(target: "scrape_task", "Mailto content failed regex check: {}", email);
+            }
+        }
+    }
+
+    let mut text_content = String::new();
+    if let Some(body_node) = document.select(&BODY_SELECTOR).next() {
+        for text_fragment in body_node.text() {
+            text_content.push_str(text_fragment.trim());
+            text_content.push(' ');
+        }
+    } else {
+        for text_fragment in document.root_element().text() {
+            text_content.push_str(text_fragment.trim());
+            text_content.push(' ');
+        }
+    }
+
+    for email_match in config.email_regex.find_iter(&text_content) {
+        let email = email_match.as_str();
+        tracing::debug!(target: "scrape_task", "Found via regex in text ({}): {}", page_url, email);
+        found_emails.insert(email.to_lowercase());
+    }
+}
+
+/// Fetches one page, following redirects.
+async fn fetch_page(http_client: &Client, page_url: &Url, config: &Config) -> PageFetch {
+    tracing::debug!(target: "scrape_task", "Attempting to GET: {}", page_url);
+
+    let response = match http_client
+        .get(page_url.clone())
+        .timeout(config.request_timeout)
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            if e.is_timeout() {
+                tracing::warn!(target: "scrape_task", "Timeout scraping {}: {}", page_url, e);
+            } else if e.is_connect() || e.is_request() {
+                tracing::warn!(target: "scrape_task", "Request/Connection error scraping {}: {}", page_url, e);
+            } else {
+                tracing::warn!(target: "scrape_task", "Unexpected error scraping {}: {}", page_url, e);
+            }
+            return PageFetch::Failed;
+        }
+    };
+
+    let status = response.status();
+    let final_url = response.url().clone();
+    if &final_url != page_url {
+        tracing::debug!(target: "scrape_task", "GET {} redirected to {}", page_url, final_url);
+    }
+    tracing::debug!(target: "scrape_task", "GET {} status: {}", final_url, status);
+
+    if !status.is_success() {
+        if status == reqwest::StatusCode::NOT_FOUND {
+            tracing::debug!(target: "scrape_task", "Page not found (404): {}", page_url);
+        } else {
+            tracing::warn!(target: "scrape_task", "HTTP error scraping {}: {}", page_url, status);
+        }
+        return PageFetch::Failed;
+    }
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|val| val.to_str().ok())
+        .unwrap_or("")
+        .to_lowercase();
+    if !content_type.contains("html") {
+        tracing::debug!(
+            target: "scrape_task",
+            "Skipping non-HTML content at {} ({})", page_url, content_type
+        );
+        return PageFetch::NotHtml;
+    }
+
+    match response.text().await {
+        Ok(content) => PageFetch::Html {
+            url: final_url,
+            content,
+        },
+        Err(e) => {
+            tracing::warn!(target: "scrape_task", "Failed to read text content from {}: {}", page_url, e);
+            PageFetch::Failed
+        }
+    }
+}
 
-    urls_to_visit.push_back(base_url.clone());
-    for page_path in &CONFIG.common_pages_to_scrape {
-        match base_url.join(page_path) {
+/// Queues the configured common pages of the site at `root`. They are
+/// queued right after the start page, ahead of any discovered link, so the
+/// crawl budget always covers them, and they are visited whatever the crawl
+/// depth.
+fn queue_common_pages(
+    root: &Url,
+    config: &Config,
+    site_hosts: &HashSet<String>,
+    seen_pages: &mut HashSet<String>,
+    urls_to_visit: &mut VecDeque<(Url, usize)>,
+) {
+    for page_path in &config.common_pages_to_scrape {
+        match root.join(page_path) {
             Ok(full_url) => {
-                if full_url.domain() == base_url.domain() {
-                    urls_to_visit.push_back(full_url);
-                } else {
+                if !site_host(&full_url).is_some_and(|host| site_hosts.contains(&host)) {
                     tracing::debug!("Skipping generated URL (different domain): {}", full_url);
+                } else if seen_pages.insert(page_key(&full_url)) {
+                    urls_to_visit.push_back((full_url, 1));
                 }
             }
             Err(e) => {
                 tracing::warn!(
                     "Failed to join base URL {} with page {}: {}",
-                    base_url,
+                    root,
                     page_path,
                     e
                 );
             }
         }
     }
+}
 
-    let initial_urls: HashSet<Url> = urls_to_visit.into_iter().collect();
-    let initial_urls_count = initial_urls.len();
-    urls_to_visit = initial_urls.into_iter().collect();
+/// Scrapes a website to find email addresses, crawling from the starting URL
+/// through the common pages and the relevant same-site links.
+///
+/// # Arguments
+/// * `http_client` - A shared `reqwest::Client` instance.
+/// * `base_url` - The starting URL of the website to scrape.
+/// * `config` - The configuration providing pages to visit, crawl limits and
+///   keywords, timeouts and the email regex.
+///
+/// # Returns
+/// * `Result<Vec<String>>` containing a list of unique, potentially valid email addresses found.
+pub(crate) async fn scrape_website_for_emails(
+    http_client: &Client,
+    base_url: &Url,
+    config: &Config,
+) -> Result<Vec<String>> {
+    let start_time = Instant::now();
+    tracing::info!(target: "scrape_task", "Starting scrape for: {}", base_url);
 
-    tracing::debug!(target: "scrape_task", "Planning to scrape {} potential URLs.", urls_to_visit.len());
+    let mut found_emails: HashSet<String> = HashSet::new();
+    let mut seen_pages: HashSet<String> = HashSet::new();
+    let mut site_hosts: HashSet<String> = site_host(base_url).into_iter().collect();
+    let mut urls_to_visit: VecDeque<(Url, usize)> = VecDeque::new();
+    let mut attempted_pages = 0;
+    let mut successful_pages = 0;
+    let mut failed_pages = 0;
 
-    let mut any_page_successful = false;
+    seen_pages.insert(page_key(base_url));
+    urls_to_visit.push_back((base_url.clone(), 0));
 
-    use once_cell::sync::Lazy;
-    static EMAIL_LINK_SELECTOR: Lazy<Selector> =
-        Lazy::new(|| Selector::parse("a[href^='mailto:']").unwrap());
+    tracing::debug!(target: "scrape_task",
+        "Crawling up to {} pages, {} links deep.", config.max_pages_to_scrape, config.max_crawl_depth
+    );
 
-    while let Some(page_url) = urls_to_visit.pop_front() {
-        let url_string = page_url.to_string();
-        if processed_urls.contains(&url_string) {
-            continue;
+    while let Some((page_url, depth)) = urls_to_visit.pop_front() {
+        if attempted_pages >= config.max_pages_to_scrape {
+            tracing::debug!(target: "scrape_task",
+                "Reached the limit of {} pages; {} queued pages left unvisited.",
+                config.max_pages_to_scrape, urls_to_visit.len() + 1
+            );
+            break;
         }
-        processed_urls.insert(url_string.clone());
-
-        tracing::debug!(target: "scrape_task", "Attempting to GET: {}", page_url);
-
-        let response_result = http_client
-            .get(page_url.clone())
-            .timeout(CONFIG.request_timeout)
-            .send()
-            .await;
-
-        match response_result {
-            Ok(response) => {
-                let status = response.status();
-                tracing::debug!(target: "scrape_task", "GET {} status: {}", page_url, status);
-
-                if status.is_success() {
-                    any_page_successful = true;
-                    let content_type = response
-                        .headers()
-                        .get(reqwest::header::CONTENT_TYPE)
-                        .and_then(|val| val.to_str().ok())
-                        .unwrap_or("")
-                        .to_lowercase();
-
-                    if !content_type.contains("html") {
-                        tracing::debug!(
-                            target: "scrape_task",
-                            "Skipping non-HTML content at {} ({})", page_url, content_type
-                        );
-                        continue;
+        attempted_pages += 1;
+
+        match fetch_page(http_client, &page_url, config).await {
+            PageFetch::Html { url, content } => {
+                if depth == 0 {
+                    // Where the start page ended up after redirects (e.g.
+                    // http → https, apex → www) is part of the site, and the
+                    // common pages are resolved against it.
+                    if let Some(host) = site_host(&url) {
+                        site_hosts.insert(host);
                     }
+                    queue_common_pages(
+                        &url,
+                        config,
+                        &site_hosts,
+                        &mut seen_pages,
+                        &mut urls_to_visit,
+                    );
+                }
+                let redirected_elsewhere = page_key(&url) != page_key(&page_url);
+                if !site_host(&url).is_some_and(|host| site_hosts.contains(&host)) {
+                    tracing::debug!(target: "scrape_task", "Skipping page redirected off-site: {}", url);
+                } else if redirected_elsewhere && !seen_pages.insert(page_key(&url)) {
+                    tracing::debug!(target: "scrape_task", "Skipping page redirected to an already visited page: {}", url);
+                } else {
+                    successful_pages += 1;
+                    let document = Html::parse_document(&content);
+                    extract_emails(&document, &url, config, &mut found_emails);
 
-                    match response.text().await {
-                        Ok(html_content) => {
-                            successful_pages += 1;
-
-                            {
-                                let document = Html::parse_document(&html_content);
-
-                                for element in document.select(&EMAIL_LINK_SELECTOR) {
-                                    if let Some(href) = element.value().attr("href") {
-                                        if let Some(email_part) = href.strip_prefix("mailto:") {
-                                            let email =
-                                                email_part.split('?').next().unwrap_or("").trim();
-                                            if !email.is_empty()
-                                                && CONFIG.email_regex.is_match(email)
-                                            {
-                                                tracing::debug!(target: "scrape_task", "Found via mailto link ({}): {}", page_url, email);
-                                                found_emails.insert(email.to_lowercase());
-                                            } else if !email.is_empty() {
-                                                tracing::warn!(target: "scrape_task", "Mailto content failed regex check: {}", email);
-                                            }
-                                        }
-                                    }
-                                }
-
-                                let mut text_content = String::new();
-
-                                let body_selector = Selector::parse("body").unwrap();
-                                if let Some(body_node) = document.select(&body_selector).next() {
-                                    for text_fragment in body_node.text() {
-                                        text_content.push_str(text_fragment.trim());
-                                        text_content.push(' ');
-                                    }
-                                } else {
-                                    for text_fragment in document.root_element().text() {
-                                        text_content.push_str(text_fragment.trim());
-                                        text_content.push(' ');
-                                    }
-                                }
-
-                                for email_match in CONFIG.email_regex.find_iter(&text_content) {
-                                    let email = email_match.as_str();
-                                    tracing::debug!(target: "scrape_task", "Found via regex in text ({}): {}", page_url, email);
-                                    found_emails.insert(email.to_lowercase());
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            tracing::warn!(target: "scrape_task", "Failed to read text content from {}: {}", page_url, e);
-                            failed_pages += 1;
-                        }
+                    if depth < config.max_crawl_depth {
+                        let mut links: Vec<(Url, u32)> =
+                            extract_links(&document, &url, &site_hosts)
+                                .into_iter()
+                                .map(|(link, text)| {
+                                    let score =
+                                        score_link(&link, &text, &config.crawl_link_keywords);
+                                    (link, score)
+                                })
+                                .filter(|(link, score)| {
+                                    *score > 0 && seen_pages.insert(page_key(link))
+                                })
+                                .collect();
+                        links.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+                        tracing::debug!(target: "scrape_task", "Queued {} relevant links from {}", links.len(), url);
+                        urls_to_visit.extend(links.into_iter().map(|(link, _)| (link, depth + 1)));
                     }
-                } else if status == reqwest::StatusCode::NOT_FOUND {
-                    failed_pages += 1;
-                    tracing::debug!(target: "scrape_task", "Page not found (404): {}", page_url);
-                } else if status.is_client_error() || status.is_server_error() {
-                    failed_pages += 1;
-                    tracing::warn!(target: "scrape_task", "HTTP error scraping {}: {}", page_url, status);
                 }
             }
-            Err(e) => {
-                failed_pages += 1;
-                if e.is_timeout() {
-                    tracing::warn!(target: "scrape_task", "Timeout scraping {}: {}", page_url, e);
-                } else if e.is_connect() || e.is_request() {
-                    tracing::warn!(target: "scrape_task", "Request/Connection error scraping {}: {}", page_url, e);
-                } else {
-                    tracing::warn!(target: "scrape_task", "Unexpected error scraping {}: {}", page_url, e);
+            outcome @ (PageFetch::NotHtml | PageFetch::Failed) if depth == 0 => {
+                if matches!(outcome, PageFetch::Failed) {
+                    failed_pages += 1;
                 }
+                queue_common_pages(
+                    base_url,
+                    config,
+                    &site_hosts,
+                    &mut seen_pages,
+                    &mut urls_to_visit,
+                );
             }
+            PageFetch::NotHtml => {}
+            PageFetch::Failed => failed_pages += 1,
         }
     }
 
-    if !any_page_successful && initial_urls_count > 0 {
+    if successful_pages == 0 {
         tracing::warn!(target: "scrape_task", "Could not successfully scrape any pages for {}", base_url);
     }
 
@@ -194,7 +379,7 @@ pub(crate) async fn scrape_website_for_emails(
         "Scrape for {} finished in {:.2?}. Attempted {} URLs ({} successful, {} failed). Found {} potentially valid emails.",
         base_url,
         elapsed,
-        successful_pages + failed_pages,
+        attempted_pages,
         successful_pages,
         failed_pages,
         filtered_emails.len()
@@ -202,3 +387,127 @@ pub(crate) async fn scrape_website_for_emails(
 
     Ok(filtered_emails)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::Filter;
+    use warp::reply::html;
+
+    #[test]
+    fn test_extract_and_score_links() {
+        let page_url: Url = "https://www.acme.com/en/".parse().unwrap();
+        let site_hosts: HashSet<String> = ["acme.com".to_string()].into_iter().collect();
+        let document = Html::parse_document(
+            r#"<body>
+                <a href="about/people#top">Our people</a>
+                <a href="http://acme.com/company/leadership">Leadership</a>
+                <a href="https://other.com/team">Partner team</a>
+                <a href="/files/team.pdf">Team brochure</a>
+                <a href="mailto:info@acme.com">Mail us</a>
+                <a href="/blog/post-1">News</a>
+            </body>"#,
+        );
+        let links = extract_links(&document, &page_url, &site_hosts);
+        let urls: Vec<&str> = links.iter().map(|(url, _)| url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://www.acme.com/en/about/people",
+                "http://acme.com/company/leadership",
+                "https://www.acme.com/blog/post-1",
+            ]
+        );
+
+        let keywords = Config::default().crawl_link_keywords;
+        let scores: Vec<u32> = links
+            .iter()
+            .map(|(url, text)| score_link(url, text, &keywords))
+            .collect();
+        // "about" and "people" in the path, "people" in the text.
+        assert_eq!(scores, vec![5, 3, 0]);
+
+        assert_eq!(
+            page_key(&"http://www.acme.com/team/".parse().unwrap()),
+            page_key(&"https://acme.com/team".parse().unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_crawl_follows_relevant_links() {
+        let routes = warp::path::end()
+            .map(|| warp::redirect::temporary(warp::http::Uri::from_static("/home")))
+            .or(warp::path("home")
+                .map(|| html(r#"<a href="/company">Company</a> <a href="/shop">Shop</a>"#)))
+            .or(warp::path("company")
+                .and(warp::path::end())
+                .map(|| html(r#"<a href="/company/leadership">Leadership</a>"#)))
+            .or(warp::path!("company" / "leadership")
+                .map(|| html(r#"<a href="/company/leadership/more-people">More</a> ceo@acme.com"#)))
+            .or(warp::path!("company" / "leadership" / "more-people").map(|| html("deep@acme.com")))
+            .or(warp::path("shop").map(|| html("shop@acme.com")));
+        let (addr, server) = warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let config = Config {
+            common_pages_to_scrape: vec![],
+            crawl_link_keywords: vec!["company".to_string(), "leadership".to_string()],
+            max_crawl_depth: 2,
+            ..Config::default()
+        };
+        let base_url: Url = format!("http://{}/", addr).parse().unwrap();
+        let emails = scrape_website_for_emails(&Client::new(), &base_url, &config)
+            .await
+            .unwrap();
+        // The start page redirects; /shop is irrelevant and the third level is
+        // beyond the depth limit.
+        assert_eq!(emails, vec!["ceo@acme.com"]);
+
+        let config = Config {
+            max_pages_to_scrape: 2,
+            ..config
+        };
+        let emails = scrape_website_for_emails(&Client::new(), &base_url, &config)
+            .await
+            .unwrap();
+        assert!(emails.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_crawl_visits_common_pages_first() {
+        let routes = warp::path::end()
+            .map(|| {
+                html(
+                    r#"<a href="/team/a">Team A</a> <a href="/team/b">Team B</a>
+                       <a href="/team/c">Team C</a>"#,
+                )
+            })
+            .or(warp::path("team").map(|| html("team@acme.com")))
+            .or(warp::path("contact").map(|| html("contact@acme.com")));
+        let (addr, server) = warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        // The budget only covers the start page and the common page.
+        let config = Config {
+            common_pages_to_scrape: vec!["/contact".to_string()],
+            max_pages_to_scrape: 2,
+            ..Config::default()
+        };
+        let base_url: Url = format!("http://{}/", addr).parse().unwrap();
+        let emails = scrape_website_for_emails(&Client::new(), &base_url, &config)
+            .await
+            .unwrap();
+        assert_eq!(emails, vec!["contact@acme.com"]);
+
+        // Without link following the common pages are still visited.
+        let config = Config {
+            max_pages_to_scrape: 25,
+            max_crawl_depth: 0,
+            ..config
+        };
+        let emails = scrape_website_for_emails(&Client::new(), &base_url, &config)
+            .await
+            .unwrap();
+        assert_eq!(emails, vec!["contact@acme.com"]);
+    }
+}
@@ -1,56 +1,289 @@
 //! Core logic for finding and verifying email addresses.
 
-use crate::config::{CONFIG, get_random_sleep_duration};
-use crate::dns::{create_resolver, resolve_mail_server};
+use crate::cache::{CacheBackend, MemoryCache, SqliteCache, VerificationCache};
+use crate::config::Config;
+use crate::deferred::DeferredVerifications;
+use crate::dns::{
+    MailServer, classify_mail_provider, create_resolver, fetch_domain_info, resolve_mail_servers,
+};
 use crate::error::{AppError, Result};
-use crate::models::{EmailResult, FoundEmailData, ValidatedContact};
-use crate::patterns::generate_email_patterns;
+use crate::formats::{AddressEvidence, FormatStore};
+use crate::models::{
+    Contact, DomainInfo, EmailResult, FoundEmailData, ProcessingResult, SmtpVerdict,
+    SmtpVerificationResult, ValidatedContact, VerificationStatus,
+};
+use crate::names::{alternative_first_names, name_variants};
+use crate::patterns::{generate_pattern_candidates, render_template};
+use crate::processor;
+use crate::resolver::MailResolver;
 use crate::scraper::scrape_website_for_emails;
-use crate::smtp::verify_email_smtp_with_retries;
+use crate::smtp::verify_emails_smtp_with_retries;
 use reqwest::Client;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc; // For sharing clients
-use std::time::Duration;
-use tokio::time::sleep;
-use trust_dns_resolver::TokioAsyncResolver;
+
+/// Confidence points for a candidate following the address format inferred
+/// for its domain.
+const FORMAT_MATCH_BONUS: i16 = 3;
+
+/// A candidate address scored before SMTP verification.
+struct AssessedCandidate {
+    email: String,
+    confidence: i16,
+    is_scraped: bool,
+    is_generic: bool,
+    name_variant: Option<String>,
+    should_verify_smtp: bool,
+}
+
+/// Finds and verifies email addresses for contacts.
+///
+/// Instances are cheap to clone and safe to share between Tokio tasks.
+/// Use [`EmailSleuth::builder`] to create one.
 #[derive(Debug, Clone)]
-pub(crate) struct EmailSleuth {
+pub struct EmailSleuth {
+    config: Arc<Config>,
     http_client: Arc<Client>,
-    dns_resolver: Arc<TokioAsyncResolver>,
+    dns_resolver: Arc<dyn MailResolver>,
+    cache: Arc<VerificationCache>,
+    formats: Arc<FormatStore>,
 }
 
-impl EmailSleuth {
-    /// Creates a new EmailSleuth instance with shared HTTP and DNS clients.
-    pub(crate) async fn new() -> Result<Self> {
-        let http_client = Arc::new(
-            Client::builder()
-                .user_agent(&CONFIG.user_agent)
-                .timeout(CONFIG.request_timeout)
+/// Builder for [`EmailSleuth`].
+#[derive(Debug, Default)]
+pub struct EmailSleuthBuilder {
+    config: Option<Config>,
+    http_client: Option<Client>,
+    cache_backend: Option<Arc<dyn CacheBackend>>,
+    dns_resolver: Option<Arc<dyn MailResolver>>,
+}
+
+impl EmailSleuthBuilder {
+    /// Sets the configuration used by the finder. Defaults to [`Config::default`].
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Uses a caller-provided HTTP client for website scraping instead of
+    /// building one from the configured user agent and timeout.
+    pub fn http_client(mut self, http_client: Client) -> Self {
+        self.http_client = Some(http_client);
+        self
+    }
+
+    /// Stores cached verification data in `backend` instead of the backend
+    /// selected by the configured cache path. Instances sharing a backend
+    /// share their cached MX lookups, catch-all analyses, scraped emails, SMTP
+    /// verdicts and learned address formats.
+    pub fn cache_backend(mut self, backend: Arc<dyn CacheBackend>) -> Self {
+        self.cache_backend = Some(backend);
+        self
+    }
+
+    /// Uses `resolver` for DNS lookups instead of the resolver described by
+    /// the configuration, e.g. a [`StaticResolver`](crate::StaticResolver) for
+    /// offline runs.
+    pub fn dns_resolver(mut self, resolver: Arc<dyn MailResolver>) -> Self {
+        self.dns_resolver = Some(resolver);
+        self
+    }
+
+    /// Creates the [`EmailSleuth`] with shared HTTP and DNS clients.
+    pub async fn build(self) -> Result<EmailSleuth> {
+        let config = self.config.unwrap_or_default();
+
+        let http_client = match self.http_client {
+            Some(client) => client,
+            None => Client::builder()
+                .user_agent(&config.user_agent)
+                .timeout(config.request_timeout)
                 .build()
                 .map_err(|e| {
                     AppError::Generic(anyhow::anyhow!("Failed to build HTTP client: {}", e))
                 })?,
-        );
+        };
 
-        let dns_resolver = Arc::new(create_resolver().await?);
+        let dns_resolver = match self.dns_resolver {
+            Some(resolver) => resolver,
+            None => create_resolver(&config).await?,
+        };
+
+        let cache_backend: Arc<dyn CacheBackend> = match (self.cache_backend, &config.cache_path) {
+            (Some(backend), _) => backend,
+            (None, Some(path)) => Arc::new(SqliteCache::open(path)?),
+            (None, None) => Arc::new(MemoryCache::new()),
+        };
+        let cache = Arc::new(VerificationCache::new(cache_backend, &config));
+        let formats = Arc::new(FormatStore::new(cache.clone()));
 
-        Ok(Self {
-            http_client,
+        Ok(EmailSleuth {
+            config: Arc::new(config),
+            http_client: Arc::new(http_client),
             dns_resolver,
+            cache,
+            formats,
         })
     }
+}
+
+/// Describes which mail server answered a probe, and over which transport,
+/// for the verification log.
+fn describe_mail_server(smtp_result: &SmtpVerificationResult) -> String {
+    match (&smtp_result.mail_server, smtp_result.tls_used) {
+        (Some(server), true) => format!(
+            " via {} (TLS, certificate: {})",
+            server,
+            smtp_result
+                .tls_certificate_subject
+                .as_deref()
+                .unwrap_or("unknown")
+        ),
+        (Some(server), false) => format!(" via {}", server),
+        (None, _) => String::new(),
+    }
+}
+
+impl EmailSleuth {
+    /// Returns a builder for configuring a new `EmailSleuth`.
+    pub fn builder() -> EmailSleuthBuilder {
+        EmailSleuthBuilder::default()
+    }
+
+    /// The configuration this instance was built with.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Processes a single input record: validates it, finds the email and
+    /// converts the findings into a [`ProcessingResult`].
+    ///
+    /// Errors are reported inside the returned result rather than as `Err`.
+    /// Greylisted candidates are not retried; they are reported with the
+    /// `Greylisted` status. Use
+    /// [`process_record_deferring`](Self::process_record_deferring) to
+    /// re-verify them after `greylist_retry_delay`.
+    pub async fn process_record(&self, record: Contact) -> ProcessingResult {
+        processor::process_record(self, record, None).await
+    }
+
+    /// Like [`process_record`](Self::process_record), but greylisted
+    /// candidates are queued on `deferred` for a later re-verification instead
+    /// of being left inconclusive.
+    ///
+    /// Call [`complete_deferred`](Self::complete_deferred) with the batch
+    /// results once every record has been processed.
+    pub async fn process_record_deferring(
+        &self,
+        record: Contact,
+        deferred: &DeferredVerifications,
+    ) -> ProcessingResult {
+        processor::process_record(self, record, Some(deferred)).await
+    }
+
+    /// Waits for the deferred re-verifications queued on `deferred` and
+    /// updates the affected entries of `results` with the later verdicts.
+    ///
+    /// # Arguments
+    /// * `deferred` - The queue passed to [`process_record_deferring`](Self::process_record_deferring).
+    /// * `results` - The results of the batch, which are patched in place.
+    ///
+    /// # Returns
+    /// * The number of records whose results changed.
+    pub async fn complete_deferred(
+        &self,
+        deferred: DeferredVerifications,
+        results: &mut [ProcessingResult],
+    ) -> usize {
+        let deferred_results = deferred.wait_all().await;
+        if deferred_results.is_empty() {
+            return 0;
+        }
+
+        let mut patched_records = 0;
+        for record in results.iter_mut() {
+            let Some(mut discovery) = record.email_discovery_results.take() else {
+                continue;
+            };
+
+            let mut changed = false;
+            for email_data in discovery.found_emails.iter_mut() {
+                // Several records may share an address, so every one of them
+                // gets the later verdict.
+                let Some(smtp_result) = deferred_results.get(&email_data.email).cloned() else {
+                    continue;
+                };
+                changed = true;
+                self.cache
+                    .store_smtp_result(&email_data.email, &smtp_result)
+                    .await;
+                tracing::info!(target: "find_email_task",
+                    "Deferred verification for {}: Status={:?}, Msg='{}'",
+                    email_data.email, smtp_result.status, smtp_result.message
+                );
+
+                // The first, greylisted attempt already earned the +1 boost for
+                // an inconclusive result.
+                let confidence = i16::from(email_data.confidence);
+                let confidence = match smtp_result.exists() {
+                    Some(true) => confidence + 4,
+                    Some(false) => 0,
+                    None => confidence,
+                };
+                email_data.confidence = confidence.clamp(0, 10) as u8;
+                email_data.verification = smtp_result.status;
+                email_data.verification_status = smtp_result.exists();
+                email_data.verification_message = smtp_result.message.clone();
+                discovery.verification_log.insert(
+                    email_data.email.clone(),
+                    format!(
+                        "{}{} (deferred retry)",
+                        smtp_result.message,
+                        describe_mail_server(&smtp_result)
+                    ),
+                );
+            }
+
+            if changed {
+                discovery.found_emails.retain(|e| e.confidence > 0);
+                self.rank_candidates(&mut discovery);
+                *record = processor::summarize_results(
+                    record.contact_input.clone(),
+                    record.parsed_name.clone(),
+                    discovery,
+                    self.config.max_alternatives,
+                );
+                patched_records += 1;
+            } else {
+                record.email_discovery_results = Some(discovery);
+            }
+        }
+        patched_records
+    }
 
     /// Finds and verifies email addresses for a given contact.
     ///
     /// This is the main entry point for the finding logic corresponding to the
-    /// Python class's `find_email` method.
+    /// Python class's `find_email` method. Greylisted candidates are not
+    /// re-verified.
     ///
     /// # Arguments
     /// * `contact` - A validated contact with required information.
     ///
     /// # Returns
     /// * `Result<EmailResult>` containing the findings.
-    pub(crate) async fn find_email(&self, contact: &ValidatedContact) -> Result<EmailResult> {
+    pub async fn find_email(&self, contact: &ValidatedContact) -> Result<EmailResult> {
+        self.find_email_with(contact, None).await
+    }
+
+    /// Finds and verifies email addresses for a given contact, queueing
+    /// greylisted candidates on `deferred` if given. Without a queue,
+    /// greylisted candidates are left with the `Greylisted` status.
+    pub(crate) async fn find_email_with(
+        &self,
+        contact: &ValidatedContact,
+        deferred: Option<&DeferredVerifications>,
+    ) -> Result<EmailResult> {
         tracing::info!(target: "find_email_task",
             "Finding email for: {} {}, Website: {}",
             contact.first_name,
@@ -62,16 +295,39 @@ impl EmailSleuth {
         let domain = &contact.domain;
 
         tracing::debug!(target: "find_email_task", "Starting pattern generation...");
-        let generated_patterns =
-            generate_email_patterns(&contact.first_name, &contact.last_name, domain);
+        let generated_patterns = generate_pattern_candidates(
+            &contact.first_name,
+            contact.middle_name.as_deref(),
+            &contact.last_name,
+            domain,
+            &self.config,
+        );
         if !generated_patterns.is_empty() {
             results.methods_used.push("pattern_generation".to_string());
             tracing::debug!(target: "find_email_task", "Finished pattern generation ({} patterns).", generated_patterns.len());
         }
 
-        tracing::debug!(target: "find_email_task", "Starting website scraping...");
-        let scraped_emails_raw =
-            scrape_website_for_emails(&self.http_client, &contact.website_url).await?;
+        // Kinds of data served from the cache, for the verification log.
+        let mut cached_lookups: Vec<&str> = Vec::new();
+
+        let scraped_emails_raw = match self.cache.scraped_emails(domain).await {
+            Some(emails) => {
+                tracing::debug!(target: "find_email_task", "Using {} cached scraped emails for {}", emails.len(), domain);
+                cached_lookups.push("scraped emails");
+                emails
+            }
+            None => {
+                tracing::debug!(target: "find_email_task", "Starting website scraping...");
+                let emails = scrape_website_for_emails(
+                    &self.http_client,
+                    &contact.website_url,
+                    &self.config,
+                )
+                .await?;
+                self.cache.store_scraped_emails(domain, &emails).await;
+                emails
+            }
+        };
         let scraped_emails: Vec<String> = scraped_emails_raw
             .iter()
             .filter(|email| {
@@ -89,12 +345,74 @@ impl EmailSleuth {
         }
         tracing::debug!(target: "find_email_task", "Finished website scraping.");
 
+        self.formats
+            .record_name(
+                domain,
+                &contact.first_name,
+                contact.middle_name.as_deref(),
+                &contact.last_name,
+            )
+            .await;
+        for email in &scraped_emails {
+            if !self.is_generic_prefix(email) {
+                self.formats
+                    .record_address(domain, email, AddressEvidence::Scraped)
+                    .await;
+            }
+        }
+        let inferred_format = self
+            .formats
+            .infer(domain, &self.config.pattern_templates)
+            .await;
+        let format_email = inferred_format
+            .as_ref()
+            .and_then(|format| {
+                render_template(
+                    &format.template,
+                    &contact.first_name,
+                    contact.middle_name.as_deref(),
+                    &contact.last_name,
+                )
+            })
+            .map(|local_part| format!("{}@{}", local_part, domain))
+            .filter(|email| generated_patterns.iter().any(|p| &p.email == email));
+        if let (Some(format), Some(email)) = (&inferred_format, &format_email) {
+            tracing::info!(target: "find_email_task",
+                "Inferred address format {} for {} from {} known addresses; ranking {} first",
+                format.template, domain, format.examples.len(), email
+            );
+            results.methods_used.push("format_inference".to_string());
+            results.verification_log.insert(
+                format!("{} (format)", domain),
+                format!(
+                    "Inferred address format {} from known addresses: {}",
+                    format.template,
+                    format.examples.join(", ")
+                ),
+            );
+        }
+
         tracing::debug!(target: "find_email_task", "Combining and ordering candidates...");
         let mut all_candidates = Vec::new();
         let mut seen_candidates = HashSet::new();
 
-        let first_lower = contact.first_name.to_lowercase();
-        let last_lower = contact.last_name.to_lowercase();
+        // Every spelling of the contact's names and the longer nicknames, to
+        // recognize addresses naming them.
+        let first_variants = name_variants(&contact.first_name);
+        let nicknames = first_variants
+            .first()
+            .map(|first| alternative_first_names(first, &self.config.nicknames))
+            .unwrap_or_default();
+        let name_tokens: Vec<String> = first_variants
+            .into_iter()
+            .chain(nicknames.into_iter().filter(|nickname| nickname.len() >= 3))
+            .chain(name_variants(&contact.last_name))
+            .collect();
+        let mentions_name = |email: &str| {
+            name_tokens
+                .iter()
+                .any(|token| email.contains(token.as_str()))
+        };
 
         let add_candidate = |email: &str, list: &mut Vec<String>, seen: &mut HashSet<String>| {
             if !email.is_empty() && seen.insert(email.to_lowercase()) {
@@ -102,23 +420,27 @@ impl EmailSleuth {
             }
         };
 
-        for p in &generated_patterns {
-            if p.contains(&first_lower) || p.contains(&last_lower) {
+        if let Some(email) = &format_email {
+            add_candidate(email, &mut all_candidates, &mut seen_candidates);
+        }
+
+        for p in generated_patterns.iter().map(|p| &p.email) {
+            if mentions_name(p) {
                 add_candidate(p, &mut all_candidates, &mut seen_candidates);
             }
         }
         for s in &scraped_emails {
-            if s.contains(&first_lower) || s.contains(&last_lower) {
+            if mentions_name(s) {
                 add_candidate(s, &mut all_candidates, &mut seen_candidates);
             }
         }
         for s in &scraped_emails {
-            if !(s.contains(&first_lower) || s.contains(&last_lower)) {
+            if !(mentions_name(s)) {
                 add_candidate(s, &mut all_candidates, &mut seen_candidates);
             }
         }
-        for p in &generated_patterns {
-            if !(p.contains(&first_lower) || p.contains(&last_lower)) {
+        for p in generated_patterns.iter().map(|p| &p.email) {
+            if !(mentions_name(p)) {
                 add_candidate(p, &mut all_candidates, &mut seen_candidates);
             }
         }
@@ -129,13 +451,47 @@ impl EmailSleuth {
         );
         tracing::debug!(target: "find_email_task", "Candidate list (ordered): {:?}", all_candidates);
 
-        let mut verified_emails_data: Vec<FoundEmailData> = Vec::new();
-        let mail_server_result = resolve_mail_server(&self.dns_resolver, domain).await;
+        let mail_servers_result = if let Some(host) = &self.config.smtp_host {
+            Ok(vec![MailServer {
+                exchange: host.clone(),
+                preference: 0,
+            }])
+        } else if let Some(servers) = self.cache.mail_servers(domain).await {
+            cached_lookups.push("MX records");
+            if servers.is_empty() {
+                Err(AppError::NullMx(domain.to_string()))
+            } else {
+                Ok(servers)
+            }
+        } else {
+            let resolved = resolve_mail_servers(self.dns_resolver.as_ref(), domain).await;
+            match &resolved {
+                Ok(servers) => self.cache.store_mail_servers(domain, servers).await,
+                Err(AppError::NullMx(_)) => self.cache.store_mail_servers(domain, &[]).await,
+                Err(_) => {}
+            }
+            resolved
+        };
 
-        let mail_server = match mail_server_result {
-            Ok(ms) => {
-                tracing::info!(target: "find_email_task", "Using mail server {} for domain {}", ms.exchange, domain);
-                Some(ms.exchange)
+        let mail_servers = match mail_servers_result {
+            Ok(servers) => {
+                tracing::info!(target: "find_email_task",
+                    "Using {} mail servers for domain {} (primary: {})",
+                    servers.len(), domain, servers[0].exchange
+                );
+                servers
+            }
+            Err(AppError::NullMx(_)) => {
+                tracing::info!(target: "find_email_task",
+                    "{} publishes a null MX and accepts no email. All candidates are invalid.",
+                    domain
+                );
+                results.verification_log.insert(
+                    domain.to_string(),
+                    "Domain does not accept email (null MX)".to_string(),
+                );
+                results.accepts_no_email = true;
+                Vec::new()
             }
             Err(e) => {
                 tracing::warn!(target: "find_email_task",
@@ -145,32 +501,119 @@ impl EmailSleuth {
                 results
                     .verification_log
                     .insert(domain.to_string(), format!("DNS resolution failed: {}", e));
-                None
+                Vec::new()
             }
         };
 
-        tracing::debug!(target: "find_email_task", "Starting candidate verification and scoring...");
+        let mail_provider =
+            classify_mail_provider(&mail_servers, domain, &self.config.mail_providers);
+        if let Some(provider) = &mail_provider {
+            tracing::info!(target: "find_email_task",
+                "Mail for {} is handled by {}", domain, provider.name
+            );
+            if provider.skip_smtp {
+                results.verification_log.insert(
+                    format!("{} (provider)", domain),
+                    format!(
+                        "SMTP verification skipped: {} gateways accept all recipients",
+                        provider.name
+                    ),
+                );
+            }
+            results.mail_provider = Some(provider.name.clone());
+        }
+        let provider_skips_smtp = mail_provider.as_ref().is_some_and(|p| p.skip_smtp);
+
+        if self.config.fetch_domain_info {
+            let domain_info = match self.cache.domain_info(domain).await {
+                Some(info) => {
+                    cached_lookups.push("domain records");
+                    info
+                }
+                None => {
+                    let info = fetch_domain_info(
+                        self.dns_resolver.as_ref(),
+                        &self.http_client,
+                        domain,
+                        results.accepts_no_email,
+                    )
+                    .await;
+                    self.cache.store_domain_info(domain, &info).await;
+                    info
+                }
+            };
+            if domain_info.declares_no_mail() {
+                tracing::warn!(target: "find_email_task",
+                    "{} publishes a null MX and 'v=spf1 -all': it declares that it uses no email",
+                    domain
+                );
+            }
+            results.domain_info = Some(domain_info);
+        }
+        let declares_no_mail = results
+            .domain_info
+            .as_ref()
+            .is_some_and(DomainInfo::declares_no_mail);
+
+        if results.accepts_no_email {
+            results.found_emails = all_candidates
+                .into_iter()
+                .filter(|email| email.ends_with(&format!("@{}", domain)))
+                .map(|email| FoundEmailData {
+                    is_generic: self.is_generic_prefix(&email),
+                    source: if scraped_emails.contains(&email) {
+                        "scraped"
+                    } else {
+                        "pattern"
+                    }
+                    .to_string(),
+                    confidence: 0,
+                    verification: VerificationStatus::Invalid,
+                    verification_status: Some(false),
+                    verification_message: if declares_no_mail {
+                        "Domain declares it uses no email (null MX and 'v=spf1 -all')"
+                    } else {
+                        "Domain does not accept email (null MX)"
+                    }
+                    .to_string(),
+                    name_variant: generated_patterns
+                        .iter()
+                        .find(|p| p.email == email)
+                        .and_then(|p| p.name_variant.clone()),
+                    email,
+                })
+                .collect();
+            if !cached_lookups.is_empty() {
+                results.verification_log.insert(
+                    format!("{} (cache)", domain),
+                    format!("Served from cache: {}", cached_lookups.join(", ")),
+                );
+            }
+            return Ok(results);
+        }
+
+        tracing::debug!(target: "find_email_task", "Starting candidate scoring...");
+        let mut assessed_candidates: Vec<AssessedCandidate> = Vec::new();
         for email in all_candidates {
-            if !CONFIG.email_regex.is_match(&email) {
+            if !self.config.email_regex.is_match(&email) {
                 tracing::warn!(target: "find_email_task", "Skipping invalid candidate format: {}", email);
                 continue;
             }
 
             tracing::debug!(target: "find_email_task", "Assessing candidate: {}", email);
             let mut confidence: i16 = 0;
-            let verification_status: Option<bool>;
-            let mut verification_message: String = "Verification not attempted".to_string();
 
             let email_parts: Vec<&str> = email.split('@').collect();
-            let email_local_part = email_parts.get(0).cloned().unwrap_or("").to_lowercase();
+            let email_local_part = email_parts.first().cloned().unwrap_or("").to_lowercase();
             let email_domain_part = email_parts.get(1).cloned().unwrap_or("").to_lowercase();
 
             let is_scraped = scraped_emails.iter().any(|s| s == &email);
-            let is_pattern = generated_patterns.iter().any(|p| p == &email);
+            let pattern = generated_patterns.iter().find(|p| p.email == email);
+            let is_pattern = pattern.is_some();
             let is_generic = self.is_generic_prefix(&email);
             let matches_primary_domain = email_domain_part == *domain;
 
-            if !matches_primary_domain && !(is_scraped && is_generic) {
+            if !(matches_primary_domain || is_scraped && is_generic) {
                 tracing::debug!(target: "find_email_task",
                    "Skipping candidate {}: Non-primary domain ({}) and not a scraped generic.",
                    email, email_domain_part
@@ -178,11 +621,13 @@ impl EmailSleuth {
                 continue;
             }
 
-            let name_in_email =
-                email_local_part.contains(&first_lower) || email_local_part.contains(&last_lower);
+            let name_in_email = mentions_name(&email_local_part);
 
-            if is_pattern && name_in_email {
-                confidence += 3;
+            if let Some(pattern) = pattern {
+                confidence += pattern.confidence_bonus();
+            }
+            if format_email.as_ref() == Some(&email) {
+                confidence += FORMAT_MATCH_BONUS;
             }
             if is_scraped && name_in_email {
                 confidence += 5;
@@ -190,9 +635,6 @@ impl EmailSleuth {
             if is_scraped && !name_in_email {
                 confidence += 2;
             }
-            if is_pattern && !name_in_email {
-                confidence += 1;
-            }
             if matches_primary_domain {
                 confidence += 1;
             }
@@ -218,42 +660,146 @@ impl EmailSleuth {
                 );
             }
 
-            let should_verify_smtp = mail_server.is_some()
+            let should_verify_smtp = !mail_servers.is_empty()
+                && !provider_skips_smtp
                 && (confidence >= 3 || (is_scraped && name_in_email && confidence > 1));
 
             tracing::debug!(target: "find_email_task",
-               "Should verify {}? {} (Confidence: {}, MailServer: {:?})",
-               email, should_verify_smtp, confidence, mail_server.is_some()
+               "Should verify {}? {} (Confidence: {}, MailServers: {})",
+               email, should_verify_smtp, confidence, mail_servers.len()
             );
 
-            let mut verification_duration_secs: f64 = 0.0;
+            assessed_candidates.push(AssessedCandidate {
+                email,
+                confidence,
+                is_scraped,
+                is_generic,
+                name_variant: pattern.and_then(|p| p.name_variant.clone()),
+                should_verify_smtp,
+            });
+        }
+
+        // However many spellings and templates produced candidates, only the
+        // most confident ones are probed.
+        let mut smtp_order: Vec<usize> = (0..assessed_candidates.len())
+            .filter(|&i| assessed_candidates[i].should_verify_smtp)
+            .collect();
+        smtp_order.sort_by_key(|&i| std::cmp::Reverse(assessed_candidates[i].confidence));
+        if smtp_order.len() > self.config.max_smtp_candidates {
+            tracing::debug!(target: "find_email_task",
+                "Verifying the {} most confident of {} candidates over SMTP",
+                self.config.max_smtp_candidates, smtp_order.len()
+            );
+            for &i in &smtp_order[self.config.max_smtp_candidates..] {
+                assessed_candidates[i].should_verify_smtp = false;
+            }
+        }
 
-            if should_verify_smtp {
-                let current_mail_server = mail_server.as_ref().unwrap();
-                if !results
-                    .methods_used
-                    .contains(&"smtp_verification".to_string())
-                {
-                    results.methods_used.push("smtp_verification".to_string());
+        let mut smtp_results = HashMap::new();
+        let mut cached_verdicts = HashSet::new();
+        let mut emails_to_verify: Vec<String> = Vec::new();
+        for candidate in assessed_candidates.iter().filter(|c| c.should_verify_smtp) {
+            match self.cache.smtp_result(&candidate.email).await {
+                Some(smtp_result) => {
+                    cached_verdicts.insert(candidate.email.clone());
+                    smtp_results.insert(candidate.email.clone(), smtp_result);
                 }
+                None => emails_to_verify.push(candidate.email.clone()),
+            }
+        }
+        if !cached_verdicts.is_empty() {
+            cached_lookups.push("SMTP verdicts");
+        }
+
+        if !smtp_results.is_empty() || !emails_to_verify.is_empty() {
+            results.methods_used.push("smtp_verification".to_string());
+        }
+        if !emails_to_verify.is_empty() {
+            tracing::debug!(target: "find_email_task",
+                "Verifying {} candidates over a shared SMTP session...", emails_to_verify.len()
+            );
+            let verify_start_time = std::time::Instant::now();
+            let session_results = verify_emails_smtp_with_retries(
+                &emails_to_verify,
+                &mail_servers,
+                self.dns_resolver.as_ref(),
+                &self.config,
+                &self.cache,
+            )
+            .await;
+            for (email, smtp_result) in &session_results {
+                self.cache.store_smtp_result(email, smtp_result).await;
+            }
+            smtp_results.extend(session_results);
+            results.verification_log.insert(
+                domain.to_string(),
+                format!(
+                    "SMTP session verified {} candidates (Took {:.2}s)",
+                    emails_to_verify.len(),
+                    verify_start_time.elapsed().as_secs_f64()
+                ),
+            );
+        }
 
-                let verify_start_time = std::time::Instant::now();
-                let (exists, message) =
-                    verify_email_smtp_with_retries(&email, &email_domain_part, current_mail_server)
-                        .await;
-                verification_duration_secs = verify_start_time.elapsed().as_secs_f64();
+        results.catch_all_confidence = smtp_results
+            .values()
+            .find_map(|result| result.catch_all_confidence);
+        if !cached_lookups.is_empty() {
+            results.verification_log.insert(
+                format!("{} (cache)", domain),
+                format!("Served from cache: {}", cached_lookups.join(", ")),
+            );
+        }
 
-                verification_status = exists;
-                verification_message = message;
+        if let Some(deferred) = deferred
+            && !self.config.greylist_retry_delay.is_zero()
+        {
+            let greylisted: Vec<String> = smtp_results
+                .iter()
+                .filter(|(_, result)| result.verdict == SmtpVerdict::Greylisted)
+                .map(|(email, _)| email.clone())
+                .collect();
+            if !greylisted.is_empty() {
                 results.verification_log.insert(
-                    email.clone(),
+                    format!("{} (greylisting)", domain),
                     format!(
-                        "{} (Took {:.2}s)",
-                        verification_message, verification_duration_secs
+                        "Re-verification of {} greylisted candidates deferred by {}s",
+                        greylisted.len(),
+                        self.config.greylist_retry_delay.as_secs()
                     ),
                 );
+                deferred.schedule(
+                    greylisted,
+                    mail_servers.clone(),
+                    self.dns_resolver.clone(),
+                    self.config.clone(),
+                    self.cache.clone(),
+                );
+            }
+        }
+
+        let mut verified_emails_data: Vec<FoundEmailData> = Vec::new();
+        for candidate in assessed_candidates {
+            let email = candidate.email;
+            let mut confidence = candidate.confidence;
+            let verification: VerificationStatus;
+            let verification_message: String;
+
+            if let Some(smtp_result) = smtp_results.remove(&email) {
+                let answered_by = describe_mail_server(&smtp_result);
+                let cache_note = if cached_verdicts.contains(&email) {
+                    " (cached)"
+                } else {
+                    ""
+                };
+                verification = smtp_result.status;
+                verification_message = smtp_result.message;
+                results.verification_log.insert(
+                    email.clone(),
+                    format!("{}{}{}", verification_message, answered_by, cache_note),
+                );
 
-                match exists {
+                match verification.exists() {
                     Some(true) => {
                         let boost = 5;
                         confidence += boost;
@@ -271,10 +817,17 @@ impl EmailSleuth {
                     }
                 }
             } else {
-                verification_status = None;
-                if mail_server.is_none() {
+                if mail_servers.is_empty() {
+                    verification = VerificationStatus::DnsFailed;
                     verification_message = "Verification skipped (DNS lookup failed)".to_string();
+                } else if let Some(provider) = mail_provider.as_ref().filter(|p| p.skip_smtp) {
+                    verification = VerificationStatus::NotAttempted;
+                    verification_message = format!(
+                        "Verification skipped ({} gateway accepts all recipients)",
+                        provider.name
+                    );
                 } else {
+                    verification = VerificationStatus::NotAttempted;
                     verification_message =
                         "Verification skipped (low initial confidence)".to_string();
                 }
@@ -283,19 +836,26 @@ impl EmailSleuth {
                     .insert(email.clone(), verification_message.clone());
             }
 
-            let final_confidence = std::cmp::max(0, std::cmp::min(10, confidence)) as u8;
+            let final_confidence = confidence.clamp(0, 10) as u8;
 
             if final_confidence > 0 {
                 tracing::debug!(target: "find_email_task",
                    "Storing final data for {}: Confidence={}, Status={:?}",
-                   email, final_confidence, verification_status
+                   email, final_confidence, verification
                 );
                 verified_emails_data.push(FoundEmailData {
                     email: email.clone(),
                     confidence: final_confidence,
-                    source: if is_scraped { "scraped" } else { "pattern" }.to_string(),
-                    is_generic,
-                    verification_status,
+                    source: if candidate.is_scraped {
+                        "scraped"
+                    } else {
+                        "pattern"
+                    }
+                    .to_string(),
+                    is_generic: candidate.is_generic,
+                    name_variant: candidate.name_variant,
+                    verification,
+                    verification_status: verification.exists(),
                     verification_message,
                 });
             } else {
@@ -303,30 +863,39 @@ impl EmailSleuth {
                    "Discarding candidate {} due to zero final confidence.", email
                 );
             }
+        }
 
-            if should_verify_smtp {
-                let base_sleep = get_random_sleep_duration();
-                let adaptive_delay = Duration::from_secs_f64(verification_duration_secs * 0.1)
-                    .clamp(Duration::ZERO, Duration::from_secs(1));
-                let total_sleep = base_sleep + adaptive_delay;
-                tracing::debug!(target: "find_email_task",
-                    "Sleeping {:?} after verification attempt for {}", total_sleep, email
-                );
-                sleep(total_sleep).await;
+        for found in &verified_emails_data {
+            if found.verification == VerificationStatus::Valid && !found.is_generic {
+                self.formats
+                    .record_address(domain, &found.email, AddressEvidence::Confirmed)
+                    .await;
             }
         }
 
+        results.found_emails = verified_emails_data;
+        self.rank_candidates(&mut results);
+
+        tracing::info!(target: "find_email_task",
+            "Finished finding email for: {} {}. Result: {:?}",
+            contact.first_name, contact.last_name, results.most_likely_email
+        );
+
+        Ok(results)
+    }
+
+    /// Sorts the found emails by likelihood and selects the most likely one
+    /// according to the confidence thresholds.
+    fn rank_candidates(&self, results: &mut EmailResult) {
         tracing::debug!(target: "find_email_task", "Sorting verified email data...");
 
-        verified_emails_data.sort_by(|a, b| {
+        results.found_emails.sort_by(|a, b| {
             b.confidence
                 .cmp(&a.confidence)
                 .then_with(|| a.is_generic.cmp(&b.is_generic))
                 .then_with(|| b.source.cmp(&a.source))
         });
 
-        results.found_emails = verified_emails_data;
-
         tracing::debug!(target: "find_email_task", "Sorted results: {:?}", results.found_emails);
 
         results.most_likely_email = None;
@@ -335,7 +904,7 @@ impl EmailSleuth {
         let best_non_generic = results
             .found_emails
             .iter()
-            .find(|e| !e.is_generic && e.confidence >= CONFIG.confidence_threshold);
+            .find(|e| !e.is_generic && e.confidence >= self.config.confidence_threshold);
 
         if let Some(email_data) = best_non_generic {
             results.most_likely_email = Some(email_data.email.clone());
@@ -345,9 +914,9 @@ impl EmailSleuth {
                email_data.email, email_data.confidence
             );
         } else if let Some(top_candidate) = results.found_emails.first() {
-            if top_candidate.confidence >= CONFIG.confidence_threshold {
+            if top_candidate.confidence >= self.config.confidence_threshold {
                 if !top_candidate.is_generic
-                    || top_candidate.confidence >= CONFIG.generic_confidence_threshold
+                    || top_candidate.confidence >= self.config.generic_confidence_threshold
                 {
                     results.most_likely_email = Some(top_candidate.email.clone());
                     results.confidence_score = top_candidate.confidence;
@@ -377,18 +946,11 @@ impl EmailSleuth {
         } else {
             tracing::info!(target: "find_email_task", "No candidates found with confidence > 0.");
         }
-
-        tracing::info!(target: "find_email_task",
-            "Finished finding email for: {} {}. Result: {:?}",
-            contact.first_name, contact.last_name, results.most_likely_email
-        );
-
-        Ok(results)
     }
 
     fn is_generic_prefix(&self, email: &str) -> bool {
         if let Some(local_part) = email.split('@').next() {
-            CONFIG
+            self.config
                 .generic_email_prefixes
                 .contains(local_part.to_lowercase().as_str())
         } else {
@@ -396,3 +958,289 @@ impl EmailSleuth {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MailProviderRule;
+    use crate::mock_smtp::{MockSmtpScript, MockSmtpServer, RcptBehavior};
+    use crate::resolver::StaticResolver;
+    use std::time::Duration;
+
+    fn contact(first_name: &str, last_name: &str, domain: &str) -> ValidatedContact {
+        ValidatedContact {
+            first_name: first_name.to_string(),
+            middle_name: None,
+            last_name: last_name.to_string(),
+            full_name: format!("{} {}", first_name, last_name),
+            // Nothing listens on port 1, so scraping fails fast and finds nothing.
+            website_url: "http://127.0.0.1:1/".parse().unwrap(),
+            domain: domain.to_string(),
+            original_contact: serde_json::from_value(serde_json::json!({})).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_find_email_against_mock_server() {
+        let server = MockSmtpServer::start(
+            MockSmtpScript::new().mailbox("jane.doe@example.com", RcptBehavior::Accept),
+        )
+        .await;
+        let sleuth = EmailSleuth::builder()
+            .config(server.config())
+            .build()
+            .await
+            .unwrap();
+        let contact = contact("Jane", "Doe", "example.com");
+
+        let results = sleuth.find_email(&contact).await.unwrap();
+        assert_eq!(
+            results.most_likely_email.as_deref(),
+            Some("jane.doe@example.com")
+        );
+        let found = &results.found_emails[0];
+        assert_eq!(found.verification, VerificationStatus::Valid);
+        assert_eq!(results.catch_all_confidence, Some(0.0));
+        assert!(
+            results
+                .found_emails
+                .iter()
+                .all(|e| e.verification != VerificationStatus::Invalid)
+        );
+
+        // The second search is answered from the cache without a new session.
+        let connections = server.connections();
+        let cached = sleuth.find_email(&contact).await.unwrap();
+        assert_eq!(server.connections(), connections);
+        assert_eq!(
+            cached.most_likely_email.as_deref(),
+            Some("jane.doe@example.com")
+        );
+        assert!(cached.verification_log["jane.doe@example.com"].ends_with("(cached)"));
+        assert!(cached.verification_log.contains_key("example.com (cache)"));
+    }
+
+    #[tokio::test]
+    async fn test_complete_deferred_updates_every_record_with_the_address() {
+        let server = MockSmtpServer::start(MockSmtpScript::new().mailbox(
+            "jane.doe@example.com",
+            RcptBehavior::Greylist { attempts: 1 },
+        ))
+        .await;
+        let config = Config {
+            greylist_retry_delay: Duration::from_millis(10),
+            ..server.config()
+        };
+        let sleuth = EmailSleuth::builder().config(config).build().await.unwrap();
+        let deferred = DeferredVerifications::new();
+
+        let found = sleuth
+            .find_email_with(&contact("Jane", "Doe", "example.com"), Some(&deferred))
+            .await
+            .unwrap();
+        let greylisted = found
+            .found_emails
+            .iter()
+            .find(|e| e.email == "jane.doe@example.com")
+            .unwrap();
+        assert_eq!(greylisted.verification, VerificationStatus::Greylisted);
+
+        // Two records of the batch ended up with the same address.
+        let record = processor::summarize_results(
+            serde_json::from_value(serde_json::json!({})).unwrap(),
+            None,
+            found,
+            5,
+        );
+        let mut results = vec![record.clone(), record];
+        assert_eq!(sleuth.complete_deferred(deferred, &mut results).await, 2);
+        for record in &results {
+            assert_eq!(record.email.as_deref(), Some("jane.doe@example.com"));
+            assert_eq!(record.email_verification, Some(VerificationStatus::Valid));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_find_email_learns_domain_format() {
+        let server = MockSmtpServer::start(
+            MockSmtpScript::new()
+                .mailbox("jdoe@example.com", RcptBehavior::Accept)
+                .mailbox("mmajor@example.com", RcptBehavior::Accept),
+        )
+        .await;
+        let sleuth = EmailSleuth::builder()
+            .config(server.config())
+            .build()
+            .await
+            .unwrap();
+
+        let first = sleuth
+            .find_email(&contact("John", "Doe", "example.com"))
+            .await
+            .unwrap();
+        assert_eq!(first.most_likely_email.as_deref(), Some("jdoe@example.com"));
+        assert!(!first.methods_used.contains(&"format_inference".to_string()));
+
+        // The confirmed jdoe@ shows the domain uses {f}{last}.
+        let second = sleuth
+            .find_email(&contact("Mary", "Major", "example.com"))
+            .await
+            .unwrap();
+        assert!(
+            second
+                .methods_used
+                .contains(&"format_inference".to_string())
+        );
+        assert_eq!(second.found_emails[0].email, "mmajor@example.com");
+        assert!(second.verification_log["example.com (format)"].contains("{f}{last}"));
+    }
+
+    #[tokio::test]
+    async fn test_find_email_skips_smtp_behind_accept_all_gateway() {
+        let server = MockSmtpServer::start(MockSmtpScript::new().catch_all()).await;
+        let config = Config {
+            mail_providers: vec![MailProviderRule {
+                name: "gateway".to_string(),
+                mx_suffixes: vec![server.host()],
+                skip_smtp: true,
+            }],
+            ..server.config()
+        };
+        let sleuth = EmailSleuth::builder().config(config).build().await.unwrap();
+
+        let results = sleuth
+            .find_email(&contact("Jane", "Doe", "example.com"))
+            .await
+            .unwrap();
+        assert_eq!(results.mail_provider.as_deref(), Some("gateway"));
+        assert_eq!(server.connections(), 0);
+        assert!(
+            results
+                .found_emails
+                .iter()
+                .all(|e| e.verification == VerificationStatus::NotAttempted)
+        );
+        assert!(
+            results
+                .verification_log
+                .contains_key("example.com (provider)")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_email_resolves_mail_servers_through_resolver() {
+        let server = MockSmtpServer::start(
+            MockSmtpScript::new().mailbox("jane.doe@example.com", RcptBehavior::Accept),
+        )
+        .await;
+        // The exchanger name exists only in the static zone, so the SMTP
+        // connection must not resolve it through the system resolver.
+        let resolver = StaticResolver::from_hosts(&format!(
+            "example.com  MX  10 mx.zone-only.invalid\n\
+             {}  mx.zone-only.invalid\n",
+            server.host()
+        ))
+        .unwrap();
+        let config = Config {
+            smtp_host: None,
+            ..server.config()
+        };
+        let sleuth = EmailSleuth::builder()
+            .config(config)
+            .dns_resolver(Arc::new(resolver))
+            .build()
+            .await
+            .unwrap();
+
+        let results = sleuth
+            .find_email(&contact("Jane", "Doe", "example.com"))
+            .await
+            .unwrap();
+        assert_eq!(
+            results.most_likely_email.as_deref(),
+            Some("jane.doe@example.com")
+        );
+        assert!(server.connections() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_find_email_verifies_only_the_most_confident_candidates() {
+        let server = MockSmtpServer::start(
+            MockSmtpScript::new().mailbox("elizabeth.muller@example.com", RcptBehavior::Accept),
+        )
+        .await;
+        let config = Config {
+            max_smtp_candidates: 3,
+            ..server.config()
+        };
+        let sleuth = EmailSleuth::builder().config(config).build().await.unwrap();
+
+        let results = sleuth
+            .find_email(&contact("Elizabeth", "Müller", "example.com"))
+            .await
+            .unwrap();
+        assert_eq!(
+            results.most_likely_email.as_deref(),
+            Some("elizabeth.muller@example.com")
+        );
+        // Catch-all probes use random local parts, so only count candidates.
+        let candidates: HashSet<String> = generate_pattern_candidates(
+            "Elizabeth",
+            None,
+            "Müller",
+            "example.com",
+            sleuth.config(),
+        )
+        .into_iter()
+        .map(|candidate| candidate.email)
+        .collect();
+        let probed_candidates = server
+            .recipients()
+            .iter()
+            .filter(|recipient| candidates.contains(*recipient))
+            .count();
+        assert!(probed_candidates <= 3, "probed {}", probed_candidates);
+    }
+
+    #[tokio::test]
+    async fn test_find_email_rejects_all_candidates_for_null_mx() {
+        let resolver = StaticResolver::from_hosts(
+            "parked.example  MX   0 .\n\
+             parked.example  A    192.0.2.90\n\
+             parked.example  TXT  \"v=spf1 -all\"\n",
+        )
+        .unwrap();
+        let config = Config {
+            request_timeout: Duration::from_millis(500),
+            sleep_between_requests: (0.0, 0.0),
+            common_pages_to_scrape: Vec::new(),
+            ..Config::default()
+        };
+        let sleuth = EmailSleuth::builder()
+            .config(config)
+            .dns_resolver(Arc::new(resolver))
+            .build()
+            .await
+            .unwrap();
+
+        let results = sleuth
+            .find_email(&contact("Jane", "Doe", "parked.example"))
+            .await
+            .unwrap();
+        assert!(results.accepts_no_email);
+        assert!(results.domain_info.unwrap().declares_no_mail());
+        assert_eq!(results.most_likely_email, None);
+        assert!(
+            results.found_emails[0]
+                .verification_message
+                .contains("v=spf1 -all")
+        );
+        assert!(!results.found_emails.is_empty());
+        assert!(
+            results
+                .found_emails
+                .iter()
+                .all(|e| e.verification == VerificationStatus::Invalid)
+        );
+    }
+}
@@ -1,458 +1,1148 @@
 //! Functions for verifying email address existence via SMTP.
 
-use crate::config::{CONFIG, get_random_sleep_duration};
+use crate::cache::VerificationCache;
+use crate::catch_all::{CatchAllAnalysis, ReplyFingerprint, random_local_parts};
+use crate::config::{Config, StartTlsMode, get_random_sleep_duration};
+use crate::dns::MailServer;
 use crate::error::{AppError, Result};
-use crate::models::SmtpVerificationResult;
-use lettre::Address;
-use lettre::transport::smtp::client::SmtpConnection;
-use lettre::transport::smtp::commands::{Ehlo, Mail, Rcpt};
-use lettre::transport::smtp::response::{Code, Severity};
-use rand::Rng;
-use std::net::ToSocketAddrs;
-use std::str::FromStr;
-use std::time::Duration;
-
-/// Performs the SMTP RCPT TO check for a single email address.
-/// This attempts to replicate the logic from the Python script's _verify_smtp function.
-/// Uses lower-level SmtpConnection for command control.
+use crate::models::{SmtpVerdict, SmtpVerificationResult, VerificationStatus};
+use crate::resolver::MailResolver;
+use crate::smtp_client::{SmtpClient, SmtpReply, TlsSessionInfo};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::net::{IpAddr, SocketAddr};
+
+/// Verifies all candidate addresses of one domain over a single SMTP session
+/// per mail exchanger, trying each exchanger in order until one answers.
 ///
 /// # Arguments
-/// * `email` - The email address to verify.
-/// * `domain` - The domain part of the email address.
-/// * `mail_server` - The hostname or IP address of the mail server obtained via DNS.
+/// * `emails` - The email addresses to verify, all at `domain`.
+/// * `domain` - The domain part of the email addresses.
+/// * `mail_servers` - The mail servers for the domain, in preference order.
+/// * `resolver` - Resolves the addresses of the mail servers.
+/// * `config` - The configuration providing the sender address and timeouts.
+/// * `cache` - Cache holding catch-all analyses of previously probed domains.
 ///
 /// # Returns
-/// * `Result<SmtpVerificationResult>` indicating whether the email likely exists,
-///   doesn't exist, or if the check was inconclusive.
-async fn verify_smtp_email(
-    email: &str,
+/// * A map from each email address to its `SmtpVerificationResult`. Each
+///   result records the mail server that answered, if any.
+async fn verify_smtp_session(
+    emails: &[String],
     domain: &str,
-    mail_server: &str,
-) -> Result<SmtpVerificationResult> {
-    tracing::debug!(target: "smtp_task",
-        "Starting SMTP check for {} via {} (Domain: {})",
-        email,
-        mail_server,
-        domain
-    );
+    mail_servers: &[MailServer],
+    resolver: &dyn MailResolver,
+    config: &Config,
+    cache: &VerificationCache,
+) -> HashMap<String, SmtpVerificationResult> {
+    let mut results: HashMap<String, SmtpVerificationResult> = HashMap::new();
+    let mut last_failure: Option<(&str, AppError)> = None;
+    let mut all_connections_blocked = true;
 
-    let recipient_address = match Address::from_str(email) {
-        Ok(addr) => addr,
-        Err(e) => {
-            tracing::error!(target: "smtp_task", "Invalid recipient email format '{}': {}", email, e);
-            return Ok(SmtpVerificationResult::conclusive(
-                false,
-                format!("Invalid email format: {}", e),
+    for mail_server in mail_servers {
+        let remaining: Vec<String> = emails
+            .iter()
+            .filter(|email| !results.contains_key(*email))
+            .cloned()
+            .collect();
+        if remaining.is_empty() {
+            break;
+        }
+
+        let target = match resolve_mail_server(&mail_server.exchange, resolver, config).await {
+            Ok(addrs) => ResolvedMailServer {
+                name: &mail_server.exchange,
+                addrs,
+            },
+            Err(e) => {
+                tracing::warn!(target: "smtp_task",
+                    "Could not resolve mail exchanger {}: {}. Trying next mail exchanger.",
+                    mail_server.exchange, e
+                );
+                all_connections_blocked = false;
+                last_failure = Some((&mail_server.exchange, e));
+                continue;
+            }
+        };
+
+        let mut outcome = probe_mail_server(
+            &remaining,
+            domain,
+            &target,
+            config,
+            cache,
+            true,
+            &mut results,
+        )
+        .await;
+
+        if config.smtp_starttls == StartTlsMode::Opportunistic
+            && matches!(outcome, Err(AppError::SmtpTls(_)))
+        {
+            tracing::warn!(target: "smtp_task",
+                "STARTTLS with {} failed, retrying the session in plaintext.", mail_server.exchange
+            );
+            let remaining: Vec<String> = emails
+                .iter()
+                .filter(|email| !results.contains_key(*email))
+                .cloned()
+                .collect();
+            outcome = probe_mail_server(
+                &remaining,
+                domain,
+                &target,
+                config,
+                cache,
                 false,
-            ));
+                &mut results,
+            )
+            .await;
         }
-    };
 
-    let sender_address = Address::from_str(&CONFIG.smtp_sender_email)
-        .map_err(|e| AppError::Config(format!("Invalid sender email in config: {}", e)))?;
+        match outcome {
+            Ok(()) => break,
+            Err(e) => {
+                tracing::warn!(target: "smtp_task",
+                    "SMTP session via {} (Pref: {}) failed: {}. Trying next mail exchanger.",
+                    mail_server.exchange, mail_server.preference, e
+                );
+                all_connections_blocked &= is_connection_blocked(&e);
+                last_failure = Some((&mail_server.exchange, e));
+            }
+        }
+    }
 
-    let socket_addr = match (mail_server, 25_u16).to_socket_addrs()?.next() {
-        Some(addr) => addr,
-        None => {
-            tracing::error!(target: "smtp_task", "Could not resolve mail server address: {}", mail_server);
-            return Ok(SmtpVerificationResult::inconclusive_no_retry(format!(
-                "Could not resolve mail server address: {}",
-                mail_server
-            )));
+    let unanswered_result = match &last_failure {
+        None => SmtpVerificationResult::inconclusive_no_retry(
+            "No mail servers available for SMTP verification".to_string(),
+        ),
+        Some(_) if all_connections_blocked => {
+            tracing::error!(target: "smtp_task",
+                "Port 25 appears to be blocked by your ISP or network. Consider using a different network or VPN.");
+            SmtpVerificationResult::inconclusive_no_retry(
+                "Port 25 is likely blocked by your ISP. Try using a different network or VPN."
+                    .to_string(),
+            )
+            .with_status(VerificationStatus::Port25Blocked)
         }
+        Some((server, error)) => handle_smtp_error(error, server),
     };
 
-    let helo_name = lettre::transport::smtp::extension::ClientId::Domain("localhost".to_string());
+    for email in emails {
+        results
+            .entry(email.clone())
+            .or_insert_with(|| unanswered_result.clone());
+    }
+    results
+}
 
-    let mut smtp_conn = match SmtpConnection::connect(
-        socket_addr,
-        Some(CONFIG.smtp_timeout),
-        &helo_name,
-        None,
-        None,
-    ) {
-        Ok(conn) => conn,
-        Err(e) => {
-            tracing::warn!(target: "smtp_task", "SMTP connection failed for {}: {}", mail_server, e);
-
-            let err_string = e.to_string();
-            if err_string.contains("timed out") || err_string.contains("connection refused") {
-                tracing::error!(target: "smtp_task", 
-                    "Port 25 appears to be blocked by your ISP or network. Consider using a different network or VPN.");
-                return Ok(SmtpVerificationResult::inconclusive_no_retry(
-                    "Port 25 is likely blocked by your ISP. Try using a different network or VPN."
-                        .to_string(),
-                ));
+/// Resolves the addresses of `mail_server` through `resolver`, so mail
+/// exchanger names are looked up like every other name: from a static zone
+/// or over DNS-over-TLS/HTTPS if configured, never through the system
+/// resolver.
+async fn resolve_mail_server(
+    mail_server: &str,
+    resolver: &dyn MailResolver,
+    config: &Config,
+) -> Result<Vec<SocketAddr>> {
+    let ips = match mail_server.parse::<IpAddr>() {
+        Ok(ip) => vec![ip],
+        Err(_) => {
+            let host = mail_server.trim_end_matches('.');
+            match tokio::time::timeout(config.smtp_timeout, resolver.lookup_ip(host)).await {
+                Ok(ips) => ips?,
+                Err(_) => {
+                    return Err(AppError::SmtpTimeout(format!(
+                        "resolving mail server address {}",
+                        mail_server
+                    )));
+                }
             }
-
-            return Ok(handle_smtp_error(&e, mail_server));
         }
     };
+    Ok(ips
+        .into_iter()
+        .map(|ip| SocketAddr::new(ip, config.smtp_port))
+        .collect())
+}
 
-    match smtp_conn.command(Ehlo::new(helo_name.clone())) {
-        Ok(_) => {
-            tracing::debug!(target: "smtp_task", "Initial EHLO successful");
-        }
-        Err(e) => {
-            tracing::warn!(target: "smtp_task", "Initial EHLO failed: {}", e);
-            return Ok(handle_smtp_error(&e, mail_server));
-        }
+/// A mail server with the socket addresses its name resolved to.
+struct ResolvedMailServer<'a> {
+    name: &'a str,
+    addrs: Vec<SocketAddr>,
+}
+
+/// Runs one SMTP session against a single mail server: EHLO and MAIL FROM
+/// once, a catch-all probe for the domain, then RCPT TO for every address.
+/// This attempts to replicate the logic from the Python script's _verify_smtp function.
+///
+/// Each answered address is inserted into `results` as soon as its reply
+/// arrives, so progress is kept if the session later fails.
+///
+/// If `allow_starttls` is set and the configured STARTTLS mode permits it,
+/// the session is upgraded when the server advertises STARTTLS.
+///
+/// # Returns
+/// * `Ok(())` once every address in `emails` has a result.
+/// * `Err(AppError)` on connection failures, timeouts or a 421 reply, in which
+///   case the next mail exchanger should be tried for the remaining addresses.
+async fn probe_mail_server(
+    emails: &[String],
+    domain: &str,
+    target: &ResolvedMailServer<'_>,
+    config: &Config,
+    cache: &VerificationCache,
+    allow_starttls: bool,
+    results: &mut HashMap<String, SmtpVerificationResult>,
+) -> Result<()> {
+    let mail_server = target.name;
+    tracing::debug!(target: "smtp_task",
+        "Starting SMTP session for {} candidates via {} (Domain: {})",
+        emails.len(),
+        mail_server,
+        domain
+    );
+
+    let helo_name = config.smtp_ehlo_hostname.as_str();
+    let local_addr = select_source_address(domain, config);
+    if let Some(local_addr) = local_addr {
+        tracing::debug!(target: "smtp_task", "Probing {} from source address {}", domain, local_addr);
     }
 
-    tracing::debug!(target: "smtp_task", "SMTP connection established to {}:{}", mail_server, socket_addr.port());
+    let (mut smtp_conn, greeting) =
+        SmtpClient::connect(mail_server, &target.addrs, local_addr, config.smtp_timeout).await?;
 
-    tracing::debug!(target: "smtp_task", "Sending MAIL FROM:<{}>...", &CONFIG.smtp_sender_email);
-    match smtp_conn.command(Mail::new(Some(sender_address.clone()), vec![])) {
-        Ok(response) => {
-            if response.is_positive() {
-                tracing::debug!(target: "smtp_task", "MAIL FROM accepted by {}: {:?}", mail_server, response);
+    if !greeting.is_positive_completion() {
+        tracing::warn!(target: "smtp_task",
+            "SMTP greeting from {} was negative: {} {}", mail_server, greeting.code, greeting.message()
+        );
+        insert_for_all(
+            results,
+            emails,
+            handle_negative_reply("Greeting", &greeting),
+            mail_server,
+            None,
+        );
+        smtp_conn.quit().await;
+        return Ok(());
+    }
+
+    let ehlo_reply = smtp_conn.ehlo(helo_name).await?;
+    if !ehlo_reply.is_positive_completion() {
+        tracing::warn!(target: "smtp_task",
+            "Initial EHLO rejected: {} {}", ehlo_reply.code, ehlo_reply.message()
+        );
+        insert_for_all(
+            results,
+            emails,
+            handle_negative_reply("EHLO", &ehlo_reply),
+            mail_server,
+            None,
+        );
+        smtp_conn.quit().await;
+        return Ok(());
+    }
+
+    if allow_starttls && config.smtp_starttls != StartTlsMode::Disabled {
+        let tls_required = config.smtp_starttls == StartTlsMode::Required;
+        if ehlo_reply.has_extension("STARTTLS") {
+            tracing::debug!(target: "smtp_task", "Upgrading session with {} via STARTTLS", mail_server);
+            let (upgraded_conn, starttls_reply) = smtp_conn.starttls(tls_required).await?;
+            smtp_conn = upgraded_conn;
+
+            if smtp_conn.tls_info().is_some() {
+                // RFC 3207: the client must discard prior knowledge and EHLO again.
+                let ehlo_reply = smtp_conn.ehlo(helo_name).await?;
+                if !ehlo_reply.is_positive_completion() {
+                    tracing::warn!(target: "smtp_task",
+                        "EHLO after STARTTLS rejected: {} {}", ehlo_reply.code, ehlo_reply.message()
+                    );
+                    insert_for_all(
+                        results,
+                        emails,
+                        handle_negative_reply("EHLO after STARTTLS", &ehlo_reply),
+                        mail_server,
+                        smtp_conn.tls_info(),
+                    );
+                    smtp_conn.quit().await;
+                    return Ok(());
+                }
+            } else if tls_required {
+                smtp_conn.quit().await;
+                return Err(AppError::SmtpTls(format!(
+                    "STARTTLS rejected by {}: {} {}",
+                    mail_server,
+                    starttls_reply.code,
+                    starttls_reply.message()
+                )));
             } else {
-                tracing::error!(target: "smtp_task",
-                    "SMTP sender '{}' rejected by {}: {:?}",
-                    &CONFIG.smtp_sender_email, mail_server, response
+                tracing::warn!(target: "smtp_task",
+                    "STARTTLS rejected by {} ({} {}), continuing in plaintext",
+                    mail_server, starttls_reply.code, starttls_reply.message()
                 );
-                smtp_conn.quit().ok();
-                return Ok(SmtpVerificationResult::inconclusive_no_retry(format!(
-                    "MAIL FROM rejected: {} {}",
-                    response.code(),
-                    response.message().collect::<Vec<&str>>().join(" ")
-                )));
             }
+        } else if tls_required {
+            smtp_conn.quit().await;
+            return Err(AppError::SmtpTls(format!(
+                "{} does not offer STARTTLS",
+                mail_server
+            )));
         }
-        Err(e) => {
-            tracing::error!(target: "smtp_task", "Error during MAIL FROM on {}: {}", mail_server, e);
-            smtp_conn.quit().ok();
-            return Ok(handle_smtp_error(&e, mail_server));
+    }
+    tracing::debug!(target: "smtp_task", "SMTP connection established to {}:{}", mail_server, config.smtp_port);
+
+    tracing::debug!(target: "smtp_task", "Sending MAIL FROM:<{}>...", &config.smtp_sender_email);
+    let mail_reply = smtp_conn.mail_from(&config.smtp_sender_email).await?;
+    if !mail_reply.is_positive() {
+        tracing::error!(target: "smtp_task",
+            "SMTP sender '{}' rejected by {}: {:?}",
+            &config.smtp_sender_email, mail_server, mail_reply
+        );
+        insert_for_all(
+            results,
+            emails,
+            SmtpVerificationResult::inconclusive_no_retry(format!(
+                "MAIL FROM rejected: {} {}",
+                mail_reply.code,
+                mail_reply.message()
+            ))
+            .with_verdict(classify_reply(&mail_reply))
+            .with_status(session_status(classify_reply(&mail_reply))),
+            mail_server,
+            smtp_conn.tls_info(),
+        );
+        smtp_conn.quit().await;
+        return Ok(());
+    }
+    tracing::debug!(target: "smtp_task", "MAIL FROM accepted by {}: {:?}", mail_server, mail_reply);
+
+    // An accepted recipient stays part of the transaction; the next RCPT
+    // then starts a fresh one.
+    let mut transaction_has_recipient = false;
+    let catch_all = match cache.catch_all(domain).await {
+        Some(analysis) => {
+            tracing::debug!(target: "smtp_task",
+                "Using cached catch-all analysis for {} (confidence {:.2})", domain, analysis.confidence
+            );
+            analysis
         }
+        None => {
+            let analysis = analyze_catch_all(
+                &mut smtp_conn,
+                domain,
+                mail_server,
+                config,
+                &mut transaction_has_recipient,
+            )
+            .await?;
+            cache.store_catch_all(domain, &analysis).await;
+            analysis
+        }
+    };
+    if catch_all.is_catch_all() {
+        tracing::warn!(target: "smtp_task",
+            "Domain {} appears to be a catch-all (confidence {:.2})", domain, catch_all.confidence
+        );
     }
 
-    tracing::debug!(target: "smtp_task", "Sending RCPT TO:<{}>...", email);
-    let rcpt_result = smtp_conn.command(Rcpt::new(recipient_address.clone(), vec![]));
+    for (index, email) in emails.iter().enumerate() {
+        if index > 0 {
+            tokio::time::sleep(get_random_sleep_duration(config)).await;
+        }
 
-    let (target_code, target_message): (Code, String) = match rcpt_result {
-        Ok(response) => (
-            response.code(),
-            response.message().collect::<Vec<&str>>().join(" "),
-        ),
-        Err(e) => {
-            let err_string = e.to_string();
-
-            let is_nonexistent_error = err_string.contains("550")
-                && (err_string.contains("does not exist")
-                    || err_string.contains("no such user")
-                    || err_string.contains("user unknown")
-                    || err_string.contains("recipient not found")
-                    || err_string.contains("NoSuchUser"));
-
-            if is_nonexistent_error {
-                tracing::info!(target: "smtp_task", 
-                    "Email not found during check for {} on {}: {}", 
-                    email, mail_server, e);
-            } else {
-                tracing::error!(target: "smtp_task", 
-                    "Error during RCPT TO for {} on {}: {}", 
-                    email, mail_server, e);
-            }
+        // Judge every candidate in its own transaction, so a server that accepts
+        // any recipient after the first valid one cannot skew the result.
+        if transaction_has_recipient
+            && !start_new_transaction(&mut smtp_conn, &config.smtp_sender_email).await
+        {
+            return Err(AppError::Smtp(format!(
+                "Could not start a new mail transaction on {}",
+                mail_server
+            )));
+        }
 
-            smtp_conn.quit().ok();
-            return Ok(handle_smtp_error(&e, mail_server));
+        tracing::debug!(target: "smtp_task", "Sending RCPT TO:<{}>...", email);
+        let reply = smtp_conn.rcpt_to(email).await?;
+        tracing::info!(target: "smtp_task",
+            "RCPT TO:<{}> result: Code={}, Msg='{}'",
+            email, reply.code, reply.message()
+        );
+        transaction_has_recipient = reply.is_positive_completion();
+
+        let fingerprint = ReplyFingerprint::new(&reply, email);
+        let matches_catch_all = catch_all.is_indistinguishable(&fingerprint);
+        let mut result = classify_rcpt_reply(&reply, matches_catch_all);
+        if catch_all.is_catch_all() && !matches_catch_all && result.exists() == Some(true) {
+            result
+                .message
+                .push_str(" (reply differs from catch-all probes)");
         }
-    };
+        result.catch_all_confidence = Some(catch_all.confidence);
+        record_session(&mut result, mail_server, smtp_conn.tls_info());
+        results.insert(email.clone(), result);
+    }
 
-    tracing::info!(target: "smtp_task",
-        "RCPT TO:<{}> result: Code={}, Msg='{}'",
-        email, target_code, target_message
-    );
+    smtp_conn.quit().await;
 
-    let mut is_catch_all = false;
-    if target_code.severity == Severity::PositiveCompletion {
-        let random_user = format!(
-            "no-reply-does-not-exist-{}@{}",
-            rand::thread_rng().gen_range(100000..999999),
-            domain
-        );
-        match Address::from_str(&random_user) {
-            Ok(random_address) => {
-                tracing::debug!(target: "smtp_task", "Checking for catch-all with: RCPT TO:<{}>", random_user);
-                match smtp_conn.command(Rcpt::new(random_address, vec![])) {
-                    Ok(response) if response.code().severity == Severity::PositiveCompletion => {
-                        is_catch_all = true;
-                        tracing::warn!(target: "smtp_task",
-                            "Domain {} appears to be a catch-all (accepted random user {} with code {})",
-                            domain, random_user, response.code()
-                        );
-                    }
-                    Ok(response) => {
-                        tracing::debug!(target: "smtp_task",
-                            "Catch-all check negative (random user rejected with code {})", response.code()
-                        );
-                    }
-                    Err(e) => {
-                        tracing::warn!(target: "smtp_task", "Error during catch-all RCPT TO check (ignoring): {}", e);
-                    }
-                }
-            }
-            Err(_) => {
-                tracing::error!(target: "smtp_task", "Failed to parse generated random email for catch-all check: {}", random_user);
-            }
+    Ok(())
+}
+
+/// Sends RCPT TO for several random recipients at `domain` and compares the
+/// replies, each in its own mail transaction.
+///
+/// # Arguments
+/// * `transaction_has_recipient` - Tracks whether the open transaction holds
+///   an accepted recipient; updated as probes are sent.
+///
+/// # Returns
+/// * The catch-all analysis for the domain.
+/// * `Err(AppError)` if the session fails while probing.
+async fn analyze_catch_all(
+    smtp_conn: &mut SmtpClient,
+    domain: &str,
+    mail_server: &str,
+    config: &Config,
+    transaction_has_recipient: &mut bool,
+) -> Result<CatchAllAnalysis> {
+    let mut probes = Vec::with_capacity(config.catch_all_probes);
+    for local_part in random_local_parts(config.catch_all_probes) {
+        if *transaction_has_recipient
+            && !start_new_transaction(smtp_conn, &config.smtp_sender_email).await
+        {
+            return Err(AppError::Smtp(format!(
+                "Could not start a new mail transaction on {}",
+                mail_server
+            )));
         }
+
+        let random_user = format!("{}@{}", local_part, domain);
+        tracing::debug!(target: "smtp_task", "Checking for catch-all with: RCPT TO:<{}>", random_user);
+        let reply = smtp_conn.rcpt_to(&random_user).await?;
+        tracing::debug!(target: "smtp_task",
+            "Catch-all probe {} answered {} {}", random_user, reply.code, reply.message()
+        );
+        *transaction_has_recipient = reply.is_positive_completion();
+        probes.push(ReplyFingerprint::new(&reply, &random_user));
     }
+    Ok(CatchAllAnalysis::from_probes(probes))
+}
 
-    let final_result = match target_code.severity {
-        Severity::PositiveCompletion => {
-            if is_catch_all {
-                SmtpVerificationResult::inconclusive_retry(format!(
-                    "SMTP accepted (Possible Catch-All): {} {}",
-                    target_code, target_message
-                ))
-            } else {
-                SmtpVerificationResult::conclusive(
-                    true,
-                    format!("SMTP Verification OK: {} {}", target_code, target_message),
-                    false,
-                )
+/// Picks the local address to probe `domain` from.
+///
+/// With a list of source addresses configured, each domain is mapped to one of
+/// them by hashing its name. This spreads domains across the egress hosts while
+/// keeping retries for the same domain on the same address, which greylisting
+/// servers track. Otherwise the configured bind address (if any) is used.
+fn select_source_address(domain: &str, config: &Config) -> Option<IpAddr> {
+    if config.smtp_source_addresses.is_empty() {
+        return config.smtp_bind_address;
+    }
+    let mut hasher = DefaultHasher::new();
+    domain.to_lowercase().hash(&mut hasher);
+    let index = (hasher.finish() % config.smtp_source_addresses.len() as u64) as usize;
+    Some(config.smtp_source_addresses[index])
+}
+
+/// Phrases that identify a rejection caused by a DNS blocklist listing of
+/// the probing host rather than by the recipient. Matched as whole words.
+const BLOCKLIST_PHRASES: [&str; 10] = [
+    "blacklist",
+    "blacklisted",
+    "blocklist",
+    "blocklisted",
+    "block list",
+    "black list",
+    "spamhaus",
+    "spamcop",
+    "barracudacentral",
+    "rbl",
+];
+
+/// Words that name greylisting explicitly.
+const GREYLIST_PHRASES: [&str; 4] = ["greylist", "graylist", "grey-list", "gray-list"];
+
+/// Generic retry wording, which only means greylisting in replies that give
+/// no more specific reason (4.2.0, 4.7.1 or no enhanced code).
+const RETRY_PHRASES: [&str; 3] = ["try again later", "retry later", "please try again"];
+
+/// Phrases used without an enhanced status code to say the mailbox does not exist.
+const UNKNOWN_USER_PHRASES: [&str; 10] = [
+    "unknown",
+    "no such",
+    "unavailable",
+    "rejected",
+    "doesn't exist",
+    "does not exist",
+    "invalid address",
+    "recipient not found",
+    "user unknown",
+    "mailbox unavailable",
+];
+
+/// Phrases used without an enhanced status code for policy rejections.
+const POLICY_PHRASES: [&str; 5] = ["policy", "spam", "relay", "access denied", "not permitted"];
+
+/// Returns whether `text` contains `phrase` as whole words, so that `rbl`
+/// does not match inside another word.
+fn contains_words(text: &str, phrase: &str) -> bool {
+    text.match_indices(phrase).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + phrase.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+/// Classifies an SMTP reply using its basic and enhanced status codes, falling
+/// back to the reply text only where the codes are ambiguous.
+fn classify_reply(reply: &SmtpReply) -> SmtpVerdict {
+    if reply.is_positive_completion() {
+        return SmtpVerdict::Accepted;
+    }
+    if reply.is_positive() {
+        return SmtpVerdict::Unknown;
+    }
+
+    let text = reply.message().to_lowercase();
+    let has_phrase = |phrases: &[&str]| phrases.iter().any(|p| text.contains(p));
+    let blocklisted = BLOCKLIST_PHRASES
+        .iter()
+        .any(|phrase| contains_words(&text, phrase));
+
+    let enhanced = reply.enhanced_code();
+    if reply.is_transient_negative() {
+        // Mailbox codes and blocklist names decide before any retry wording:
+        // retrying a full mailbox or a listed host minutes later gets the
+        // same reply.
+        return match enhanced.map(|e| (e.subject, e.detail)) {
+            Some((2, 1)) => SmtpVerdict::MailboxBusy,
+            Some((2, 2)) => SmtpVerdict::MailboxFull,
+            _ if blocklisted => SmtpVerdict::Blacklisted,
+            _ if has_phrase(&GREYLIST_PHRASES) => SmtpVerdict::Greylisted,
+            Some((2, 0)) | Some((7, 1)) | None if has_phrase(&RETRY_PHRASES) => {
+                SmtpVerdict::Greylisted
             }
+            // 4.7.x without greylisting wording is a temporary policy rejection.
+            Some((7, _)) => SmtpVerdict::PolicyBlock,
+            _ => SmtpVerdict::TemporaryFailure,
+        };
+    }
+
+    // The mailbox codes are definitive whatever the text says, so a blocklist
+    // name appearing in e.g. a host name does not hide an unknown user.
+    match enhanced.map(|e| (e.subject, e.detail)) {
+        Some((1, 0 | 1 | 3 | 6)) => SmtpVerdict::UnknownUser,
+        Some((2, 1)) => SmtpVerdict::MailboxDisabled,
+        Some((2, 2)) => SmtpVerdict::MailboxFull,
+        _ if blocklisted => SmtpVerdict::Blacklisted,
+        Some((7, _)) | Some((1, 8)) => SmtpVerdict::PolicyBlock,
+        Some(_) => SmtpVerdict::Rejected,
+        None if reply.code == 552 => SmtpVerdict::MailboxFull,
+        None if has_phrase(&POLICY_PHRASES) => SmtpVerdict::PolicyBlock,
+        None if [550, 551, 553].contains(&reply.code) || has_phrase(&UNKNOWN_USER_PHRASES) => {
+            SmtpVerdict::UnknownUser
         }
-        Severity::PositiveIntermediate => SmtpVerificationResult::inconclusive_retry(format!(
-            "SMTP Unexpected Intermediate Code: {} {}",
-            target_code, target_message
-        )),
-        Severity::TransientNegativeCompletion => {
-            SmtpVerificationResult::inconclusive_retry(format!(
-                "SMTP Temp Failure/Greylisted? (4xx): {} {}",
-                target_code, target_message
+        None => SmtpVerdict::Rejected,
+    }
+}
+
+/// Interprets the reply to a candidate's RCPT TO command.
+///
+/// `matches_catch_all` is true if the domain is a catch-all and the reply
+/// cannot be told apart from the replies to random recipients.
+fn classify_rcpt_reply(reply: &SmtpReply, matches_catch_all: bool) -> SmtpVerificationResult {
+    let verdict = classify_reply(reply);
+    let reply_text = format!("{} {}", reply.code, reply.message());
+
+    let result = match verdict {
+        SmtpVerdict::Accepted if matches_catch_all => {
+            SmtpVerificationResult::inconclusive_no_retry(format!(
+                "SMTP accepted (Possible Catch-All): {}",
+                reply_text
             ))
+            .with_status(VerificationStatus::CatchAll)
         }
-        Severity::PermanentNegativeCompletion => {
-            let rejection_phrases = [
-                "unknown",
-                "no such",
-                "unavailable",
-                "rejected",
-                "doesn't exist",
-                "disabled",
-                "invalid address",
-                "recipient not found",
-                "user unknown",
-                "mailbox unavailable",
-            ];
-            let message_lower = target_message.to_lowercase();
-
-            let code_value = u16::from(target_code);
-
-            if [550, 551, 553].contains(&code_value)
-                || rejection_phrases.iter().any(|p| message_lower.contains(p))
-            {
-                SmtpVerificationResult::conclusive(
-                    false,
-                    format!(
-                        "SMTP Rejected (User Likely Unknown): {} {}",
-                        target_code, target_message
-                    ),
-                    false,
-                )
-            } else {
-                SmtpVerificationResult::conclusive(
-                    false,
-                    format!(
-                        "SMTP Rejected (Policy/Other 5xx): {} {}",
-                        target_code, target_message
-                    ),
-                    false,
-                )
-            }
+        SmtpVerdict::Accepted => SmtpVerificationResult::conclusive(
+            true,
+            format!("SMTP Verification OK: {}", reply_text),
+        ),
+        SmtpVerdict::UnknownUser => SmtpVerificationResult::conclusive(
+            false,
+            format!("SMTP Rejected (User Unknown): {}", reply_text),
+        ),
+        SmtpVerdict::MailboxDisabled => SmtpVerificationResult::conclusive(
+            false,
+            format!("SMTP Rejected (Mailbox Disabled): {}", reply_text),
+        )
+        .with_status(VerificationStatus::Disabled),
+        // A full mailbox still exists; it just cannot take mail right now.
+        SmtpVerdict::MailboxFull => {
+            SmtpVerificationResult::conclusive(true, format!("SMTP Mailbox Full: {}", reply_text))
+                .with_status(VerificationStatus::MailboxFull)
+        }
+        SmtpVerdict::MailboxBusy => {
+            SmtpVerificationResult::inconclusive_retry(format!("SMTP Mailbox Busy: {}", reply_text))
+        }
+        SmtpVerdict::PolicyBlock => SmtpVerificationResult::inconclusive_no_retry(format!(
+            "SMTP Blocked by Policy: {}",
+            reply_text
+        ))
+        .with_status(VerificationStatus::PolicyBlocked),
+        SmtpVerdict::Blacklisted => SmtpVerificationResult::inconclusive_no_retry(format!(
+            "SMTP Blocked (Sender Blacklisted): {}",
+            reply_text
+        ))
+        .with_status(VerificationStatus::PolicyBlocked),
+        SmtpVerdict::Greylisted => {
+            SmtpVerificationResult::inconclusive_retry(format!("SMTP Greylisted: {}", reply_text))
+                .with_status(VerificationStatus::Greylisted)
         }
+        SmtpVerdict::TemporaryFailure => SmtpVerificationResult::inconclusive_retry(format!(
+            "SMTP Temporary Failure (4xx): {}",
+            reply_text
+        )),
+        SmtpVerdict::Rejected => SmtpVerificationResult::conclusive(
+            false,
+            format!("SMTP Rejected (Other 5xx): {}", reply_text),
+        ),
+        SmtpVerdict::Unknown => SmtpVerificationResult::inconclusive_retry(format!(
+            "SMTP Unexpected Intermediate Code: {}",
+            reply_text
+        )),
     };
+    result.with_verdict(verdict)
+}
 
-    smtp_conn
-        .quit()
-        .map_err(|e| {
-            tracing::warn!(target: "smtp_task", "Error during SMTP QUIT: {}", e);
-            AppError::Smtp(e)
-        })
-        .ok();
-
-    Ok(final_result)
+/// Records which mail server answered and whether the session used TLS.
+fn record_session(
+    result: &mut SmtpVerificationResult,
+    mail_server: &str,
+    tls_info: Option<&TlsSessionInfo>,
+) {
+    result.mail_server = Some(mail_server.to_string());
+    result.tls_used = tls_info.is_some();
+    result.tls_certificate_subject = tls_info.and_then(|info| info.certificate_subject.clone());
 }
 
-/// Helper function to interpret lettre::transport::smtp::Error into SmtpVerificationResult
-fn handle_smtp_error(
-    error: &lettre::transport::smtp::Error,
-    server: &str,
-) -> SmtpVerificationResult {
-    let err_string = error.to_string();
-
-    if err_string.contains("550")
-        && (err_string.contains("does not exist")
-            || err_string.contains("no such user")
-            || err_string.contains("user unknown")
-            || err_string.contains("recipient not found")
-            || err_string.contains("NoSuchUser"))
-    {
-        return SmtpVerificationResult::conclusive(
-            false,
-            format!("SMTP Rejected (User Does Not Exist): {}", err_string),
-            false,
-        );
+/// Records the same session-level outcome for every address in `emails`.
+fn insert_for_all(
+    results: &mut HashMap<String, SmtpVerificationResult>,
+    emails: &[String],
+    result: SmtpVerificationResult,
+    mail_server: &str,
+    tls_info: Option<&TlsSessionInfo>,
+) {
+    for email in emails {
+        let mut email_result = result.clone();
+        record_session(&mut email_result, mail_server, tls_info);
+        results.insert(email.clone(), email_result);
     }
+}
 
-    if err_string.contains("4")
-        && (err_string.contains("temporary") || err_string.contains("transient"))
-    {
-        return SmtpVerificationResult::inconclusive_retry(format!(
-            "SMTP Transient Error: {}",
-            err_string
-        ));
+/// Resets the session and starts a new mail transaction, so that the next
+/// RCPT is not judged together with recipients already accepted.
+///
+/// Returns `false` if the server did not accept the RSET or the new MAIL FROM.
+async fn start_new_transaction(smtp_conn: &mut SmtpClient, sender: &str) -> bool {
+    match smtp_conn.rset().await {
+        Ok(reply) if reply.is_positive_completion() => {}
+        Ok(reply) => {
+            tracing::debug!(target: "smtp_task", "RSET rejected: {} {}", reply.code, reply.message());
+            return false;
+        }
+        Err(e) => {
+            tracing::debug!(target: "smtp_task", "Error during RSET: {}", e);
+            return false;
+        }
     }
-
-    if err_string.contains("5") && err_string.contains("permanent") {
-        return SmtpVerificationResult::inconclusive_no_retry(format!(
-            "SMTP Permanent Error: {}",
-            err_string
-        ));
+    match smtp_conn.mail_from(sender).await {
+        Ok(reply) if reply.is_positive() => true,
+        Ok(reply) => {
+            tracing::debug!(target: "smtp_task", "MAIL FROM after RSET rejected: {} {}", reply.code, reply.message());
+            false
+        }
+        Err(e) => {
+            tracing::debug!(target: "smtp_task", "Error during MAIL FROM after RSET: {}", e);
+            false
+        }
     }
+}
 
-    if err_string.contains("connection refused") {
-        return SmtpVerificationResult::inconclusive_no_retry(format!(
-            "Connection refused by {}",
-            server
-        ));
+/// True if a connection error suggests outbound port 25 is blocked
+/// (the connection timed out or was actively refused). A server that stops
+/// answering after the connection was established was reachable, so later
+/// timeouts do not count.
+fn is_connection_blocked(error: &AppError) -> bool {
+    match error {
+        AppError::SmtpTimeout(context) => context.starts_with("connecting to"),
+        AppError::Io(e) => e.kind() == std::io::ErrorKind::ConnectionRefused,
+        _ => false,
     }
+}
 
-    if err_string.contains("connection reset") {
-        return SmtpVerificationResult::inconclusive_retry(format!(
-            "Connection reset by {}",
-            server
-        ));
-    }
+/// Interprets a negative reply to a session-level command (greeting, EHLO)
+/// into an inconclusive `SmtpVerificationResult`.
+fn handle_negative_reply(stage: &str, reply: &SmtpReply) -> SmtpVerificationResult {
+    let message = format!(
+        "SMTP {} rejected: {} {}",
+        stage,
+        reply.code,
+        reply.message()
+    );
+    let result = if reply.is_transient_negative() {
+        SmtpVerificationResult::inconclusive_retry(message)
+    } else {
+        SmtpVerificationResult::inconclusive_no_retry(message)
+    };
+    let verdict = classify_reply(reply);
+    result
+        .with_verdict(verdict)
+        .with_status(session_status(verdict))
+}
 
-    if err_string.contains("timed out") {
-        return SmtpVerificationResult::inconclusive_retry(
-            "SMTP connection/operation timed out".to_string(),
-        );
+/// Maps the verdict on a session-level reply (greeting, EHLO, MAIL FROM) to a
+/// status for every address of the session.
+fn session_status(verdict: SmtpVerdict) -> VerificationStatus {
+    match verdict {
+        SmtpVerdict::PolicyBlock | SmtpVerdict::Blacklisted => VerificationStatus::PolicyBlocked,
+        SmtpVerdict::Greylisted => VerificationStatus::Greylisted,
+        _ => VerificationStatus::Inconclusive,
     }
+}
 
-    if err_string.contains("TLS") || err_string.contains("tls") {
-        tracing::warn!(target: "smtp_task", "SMTP TLS Error for {}: {:?}", server, error);
-        return SmtpVerificationResult::inconclusive_retry(format!("SMTP TLS Error: {:?}", error));
+/// Helper function to interpret an SMTP client error into SmtpVerificationResult
+fn handle_smtp_error(error: &AppError, server: &str) -> SmtpVerificationResult {
+    match error {
+        AppError::Io(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+            SmtpVerificationResult::inconclusive_no_retry(format!(
+                "Connection refused by {}",
+                server
+            ))
+        }
+        AppError::Io(e)
+            if matches!(
+                e.kind(),
+                std::io::ErrorKind::ConnectionReset | std::io::ErrorKind::ConnectionAborted
+            ) =>
+        {
+            SmtpVerificationResult::inconclusive_retry(format!("Connection reset by {}", server))
+        }
+        AppError::SmtpTimeout(context) => SmtpVerificationResult::inconclusive_retry(format!(
+            "SMTP connection/operation timed out ({})",
+            context
+        ))
+        .with_status(VerificationStatus::Timeout),
+        AppError::SmtpCommand { code, message } => {
+            SmtpVerificationResult::inconclusive_retry(format!(
+                "SMTP Service Unavailable on {}: {} {}",
+                server, code, message
+            ))
+        }
+        AppError::SmtpTls(message) => {
+            tracing::warn!(target: "smtp_task", "SMTP TLS Error for {}: {}", server, message);
+            SmtpVerificationResult::inconclusive_retry(format!("SMTP TLS Error: {}", message))
+        }
+        _ => {
+            tracing::error!(target: "smtp_task", "Unhandled SMTP Error ({}) : {}", server, error);
+            SmtpVerificationResult::inconclusive_retry(format!("Unhandled SMTP Error: {}", error))
+        }
     }
-
-    tracing::error!(target: "smtp_task", "Unhandled SMTP Error ({}) : {}", server, error);
-    SmtpVerificationResult::inconclusive_retry(format!("Unhandled SMTP Error: {}", error))
 }
 
-/// Verifies an email using SMTP with retries for inconclusive results.
+/// Verifies a set of emails using SMTP, reusing one session per domain and
+/// retrying inconclusive results.
 ///
 /// # Arguments
-/// * `email` - The email address to verify.
-/// * `domain` - The domain part of the email address.
-/// * `mail_server` - The hostname or IP address of the mail server.
+/// * `emails` - The email addresses to verify.
+/// * `mail_servers` - The mail servers to probe, in preference order.
+/// * `resolver` - Resolves the addresses of the mail servers.
+/// * `config` - The configuration providing retry limits and SMTP settings.
+/// * `cache` - Cache holding catch-all analyses shared across verifications.
 ///
 /// # Returns
-/// * `(Option<bool>, String)`: Tuple containing the verification status (Some(true), Some(false), or None)
-///   and a final descriptive message.
-pub(crate) async fn verify_email_smtp_with_retries(
-    email: &str,
-    domain: &str,
-    mail_server: &str,
-) -> (Option<bool>, String) {
-    let mut last_result: Option<bool> = None;
-    let mut last_message = "SMTP check did not run or complete".to_string();
+/// * A map from each email address to the `SmtpVerificationResult` of its last
+///   attempt: the verification status (Some(true), Some(false), or None), a
+///   final descriptive message and the mail server that answered.
+pub(crate) async fn verify_emails_smtp_with_retries(
+    emails: &[String],
+    mail_servers: &[MailServer],
+    resolver: &dyn MailResolver,
+    config: &Config,
+    cache: &VerificationCache,
+) -> HashMap<String, SmtpVerificationResult> {
+    let mut final_results: HashMap<String, SmtpVerificationResult> = HashMap::new();
 
-    for attempt in 0..CONFIG.max_verification_attempts {
-        tracing::info!(target: "smtp_task",
-            "Attempt {}/{} SMTP check for {} via {}",
-            attempt + 1,
-            CONFIG.max_verification_attempts,
-            email,
-            mail_server
-        );
+    // Group by domain so the catch-all probe targets the right domain.
+    let mut pending: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for email in emails {
+        if !config.email_regex.is_match(email) {
+            tracing::error!(target: "smtp_task", "Invalid recipient email format '{}'", email);
+            final_results.insert(
+                email.clone(),
+                SmtpVerificationResult::conclusive(
+                    false,
+                    format!("Invalid email format: {}", email),
+                ),
+            );
+            continue;
+        }
+        if !config.email_regex.is_match(&config.smtp_sender_email) {
+            final_results.insert(
+                email.clone(),
+                SmtpVerificationResult::inconclusive_no_retry(format!(
+                    "Invalid sender email in config: {}",
+                    config.smtp_sender_email
+                )),
+            );
+            continue;
+        }
+        let domain = email.rsplit_once('@').map(|(_, d)| d).unwrap_or_default();
+        pending
+            .entry(domain.to_string())
+            .or_default()
+            .push(email.clone());
+    }
+
+    for attempt in 0..config.max_verification_attempts {
+        if pending.is_empty() {
+            break;
+        }
+
+        let mut still_pending: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (domain, domain_emails) in &pending {
+            tracing::info!(target: "smtp_task",
+                "Attempt {}/{} SMTP session for {} candidates at {} ({} mail servers)",
+                attempt + 1,
+                config.max_verification_attempts,
+                domain_emails.len(),
+                domain,
+                mail_servers.len()
+            );
 
-        match verify_smtp_email(email, domain, mail_server).await {
-            Ok(result) => {
-                last_result = result.exists;
-                last_message = result.message.clone();
+            let session_results =
+                verify_smtp_session(domain_emails, domain, mail_servers, resolver, config, cache)
+                    .await;
 
-                if result.exists.is_some() {
+            for (email, result) in session_results {
+                if result.exists().is_some() {
                     tracing::debug!(target: "smtp_task",
-                        "SMTP check conclusive (Result: {:?}) on attempt {}.",
-                        result.exists, attempt + 1
+                        "SMTP check for {} conclusive (Result: {:?}) on attempt {}.",
+                        email, result.status, attempt + 1
                     );
-                    break;
-                }
-
-                if !result.should_retry {
+                } else if !result.should_retry {
                     tracing::warn!(target: "smtp_task",
-                        "SMTP check failed with non-retriable status on attempt {}. Stopping. Msg: {}",
-                         attempt + 1, result.message
+                        "SMTP check for {} failed with non-retriable status on attempt {}. Msg: {}",
+                        email, attempt + 1, result.message
                     );
-                    break;
-                }
-
-                tracing::warn!(target: "smtp_task",
-                    "SMTP check inconclusive on attempt {}. Message: {}",
-                     attempt + 1, result.message
-                );
-            }
-            Err(e) => {
-                tracing::error!(target: "smtp_task",
-                    "Error during SMTP verification attempt {}: {}", attempt + 1, e
-                );
-                last_message = format!("Internal error during SMTP check: {}", e);
-                if attempt >= CONFIG.max_verification_attempts - 1 {
-                    last_result = None;
+                } else if result.verdict == SmtpVerdict::Greylisted {
+                    // Greylisting needs minutes, not the short pause between attempts.
+                    tracing::info!(target: "smtp_task",
+                        "SMTP check for {} greylisted on attempt {}. Not retrying immediately. Msg: {}",
+                        email, attempt + 1, result.message
+                    );
+                } else {
+                    tracing::warn!(target: "smtp_task",
+                        "SMTP check for {} inconclusive on attempt {} (Catch-all confidence: {:?}). Message: {}",
+                        email, attempt + 1, result.catch_all_confidence, result.message
+                    );
+                    still_pending
+                        .entry(domain.clone())
+                        .or_default()
+                        .push(email.clone());
                 }
+                final_results.insert(email, result);
             }
         }
+        pending = still_pending;
 
-        if attempt < CONFIG.max_verification_attempts - 1 && last_result.is_none() {
-            let sleep_duration = get_random_sleep_duration();
+        if attempt < config.max_verification_attempts - 1 && !pending.is_empty() {
+            let sleep_duration = get_random_sleep_duration(config);
             tracing::debug!(target: "smtp_task", "Sleeping {:?} before next SMTP attempt.", sleep_duration);
             tokio::time::sleep(sleep_duration).await;
         }
     }
 
-    tracing::info!(target: "smtp_task",
-        "Final SMTP verification result for {}: Status={:?}, Msg='{}'",
-        email, last_result, last_message
-    );
+    for (email, result) in &final_results {
+        tracing::info!(target: "smtp_task",
+            "Final SMTP verification result for {}: Status={:?}, Verdict={:?}, Via={:?}, TLS={}, Msg='{}'",
+            email, result.status, result.verdict, result.mail_server, result.tls_used, result.message
+        );
+    }
 
-    (last_result, last_message)
+    final_results
 }
 
-pub(crate) async fn test_smtp_connectivity() -> Result<()> {
-    tracing::info!("Testing SMTP connectivity...");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::MemoryCache;
+    use crate::mock_smtp::{ConnectBehavior, MockSmtpScript, MockSmtpServer, RcptBehavior};
+    use crate::resolver::StaticResolver;
+    use std::sync::Arc;
 
-    let socket_addr = match ("gmail-smtp-in.l.google.com", 25_u16)
-        .to_socket_addrs()
-        .ok()
-        .and_then(|mut addrs| addrs.next())
-    {
-        Some(addr) => addr,
-        None => {
-            return Err(AppError::Config(
-                "Could not resolve gmail-smtp-in.l.google.com".to_string(),
-            ));
+    fn reply(code: u16, text: &str) -> SmtpReply {
+        SmtpReply {
+            code,
+            lines: vec![text.to_string()],
         }
-    };
+    }
 
-    let helo_name = lettre::transport::smtp::extension::ClientId::Domain("localhost".to_string());
+    #[test]
+    fn test_select_source_address() {
+        let mut config = Config::default();
+        assert_eq!(select_source_address("example.com", &config), None);
 
-    let timeout = Duration::from_secs(5);
+        let bind: IpAddr = "192.0.2.1".parse().unwrap();
+        config.smtp_bind_address = Some(bind);
+        assert_eq!(select_source_address("example.com", &config), Some(bind));
 
-    match tokio::time::timeout(timeout, async {
-        SmtpConnection::connect(socket_addr, Some(timeout), &helo_name, None, None)
-    })
-    .await
-    {
-        Ok(Ok(_)) => {
-            tracing::info!("SMTP connectivity test successful");
-            Ok(())
-        }
-        Ok(Err(e)) => Err(AppError::Smtp(e)),
-        Err(_) => Err(AppError::SmtpInconclusive(
-            "SMTP connection timed out - port 25 is likely blocked by your ISP or network provider"
-                .to_string(),
-        )),
+        config.smtp_source_addresses = vec![
+            "198.51.100.1".parse().unwrap(),
+            "198.51.100.2".parse().unwrap(),
+            "2001:db8::1".parse().unwrap(),
+        ];
+        let selected = select_source_address("example.com", &config).unwrap();
+        assert!(config.smtp_source_addresses.contains(&selected));
+        assert_eq!(
+            select_source_address("EXAMPLE.com", &config),
+            Some(selected)
+        );
+    }
+
+    #[test]
+    fn test_classify_reply_enhanced_codes() {
+        assert_eq!(
+            classify_reply(&reply(
+                550,
+                "5.1.1 <jane@example.com>: Recipient address rejected"
+            )),
+            SmtpVerdict::UnknownUser
+        );
+        assert_eq!(
+            classify_reply(&reply(552, "5.2.2 Mailbox full")),
+            SmtpVerdict::MailboxFull
+        );
+        assert_eq!(
+            classify_reply(&reply(550, "5.2.1 Mailbox disabled")),
+            SmtpVerdict::MailboxDisabled
+        );
+        assert_eq!(
+            classify_reply(&reply(450, "4.2.1 Mailbox busy")),
+            SmtpVerdict::MailboxBusy
+        );
+        assert_eq!(
+            classify_reply(&reply(550, "5.7.1 Relaying denied")),
+            SmtpVerdict::PolicyBlock
+        );
+        assert_eq!(
+            classify_reply(&reply(
+                554,
+                "5.7.1 Service unavailable; Client host blocked using zen.spamhaus.org"
+            )),
+            SmtpVerdict::Blacklisted
+        );
+        assert_eq!(
+            classify_reply(&reply(
+                450,
+                "4.2.0 Recipient address rejected: Greylisted, see http://postgrey"
+            )),
+            SmtpVerdict::Greylisted
+        );
+        assert_eq!(
+            classify_reply(&reply(451, "4.7.1 Please try again later")),
+            SmtpVerdict::Greylisted
+        );
+        // Retry wording does not override the mailbox codes or a blocklist.
+        assert_eq!(
+            classify_reply(&reply(452, "4.2.2 Mailbox full, try again later")),
+            SmtpVerdict::MailboxFull
+        );
+        assert_eq!(
+            classify_reply(&reply(450, "4.2.1 Mailbox busy, retry later")),
+            SmtpVerdict::MailboxBusy
+        );
+        assert_eq!(
+            classify_reply(&reply(
+                451,
+                "4.7.1 Client host listed by Spamhaus, please try again later"
+            )),
+            SmtpVerdict::Blacklisted
+        );
+        assert_eq!(
+            classify_reply(&reply(451, "4.3.0 Temporary lookup failure")),
+            SmtpVerdict::TemporaryFailure
+        );
+        // 4.7.1 alone is a policy rejection, not greylisting.
+        assert_eq!(
+            classify_reply(&reply(
+                450,
+                "4.7.1 Client host rejected: cannot find your hostname"
+            )),
+            SmtpVerdict::PolicyBlock
+        );
+        // The enhanced code wins over blocklist names in the text, which
+        // only match as whole words.
+        assert_eq!(
+            classify_reply(&reply(
+                550,
+                "5.1.1 <jane@example.com>: unknown user (mx1.barracudacentral.example)"
+            )),
+            SmtpVerdict::UnknownUser
+        );
+        assert_eq!(
+            classify_reply(&reply(550, "5.7.1 Message refused by the Garbles filter")),
+            SmtpVerdict::PolicyBlock
+        );
+        assert_eq!(
+            classify_reply(&reply(554, "Blocked, see https://www.spamcop.net/bl.shtml")),
+            SmtpVerdict::Blacklisted
+        );
+    }
+
+    #[test]
+    fn test_classify_reply_without_enhanced_code() {
+        // A digit 4 in the text must not make a permanent failure look temporary.
+        assert_eq!(
+            classify_reply(&reply(550, "User 4444 unknown")),
+            SmtpVerdict::UnknownUser
+        );
+        assert_eq!(
+            classify_reply(&reply(554, "Message rejected by policy")),
+            SmtpVerdict::PolicyBlock
+        );
+        assert_eq!(
+            classify_reply(&reply(554, "Go away")),
+            SmtpVerdict::Rejected
+        );
+        assert_eq!(
+            classify_reply(&reply(354, "Go ahead")),
+            SmtpVerdict::Unknown
+        );
+    }
+
+    #[test]
+    fn test_classify_rcpt_reply() {
+        let accepted = classify_rcpt_reply(&reply(250, "2.1.5 Ok"), false);
+        assert_eq!(accepted.status, VerificationStatus::Valid);
+        assert_eq!(accepted.verdict, SmtpVerdict::Accepted);
+
+        let catch_all = classify_rcpt_reply(&reply(250, "2.1.5 Ok"), true);
+        assert_eq!(catch_all.status, VerificationStatus::CatchAll);
+        assert_eq!(catch_all.exists(), None);
+
+        let unknown = classify_rcpt_reply(&reply(550, "5.1.1 User unknown"), false);
+        assert_eq!(unknown.status, VerificationStatus::Invalid);
+
+        let greylisted = classify_rcpt_reply(&reply(451, "4.7.1 Try again later"), false);
+        assert_eq!(greylisted.status, VerificationStatus::Greylisted);
+        assert!(greylisted.should_retry);
+        assert_eq!(greylisted.verdict, SmtpVerdict::Greylisted);
+
+        let blocked = classify_rcpt_reply(&reply(550, "5.7.1 Rejected by policy"), false);
+        assert_eq!(blocked.status, VerificationStatus::PolicyBlocked);
+        assert_eq!(blocked.exists(), None);
+        assert!(!blocked.should_retry);
+    }
+
+    async fn verify_with_mock(
+        script: MockSmtpScript,
+        emails: &[&str],
+    ) -> (MockSmtpServer, HashMap<String, SmtpVerificationResult>) {
+        let server = MockSmtpServer::start(script).await;
+        let config = server.config();
+        let cache = VerificationCache::new(Arc::new(MemoryCache::new()), &config);
+        let resolver = StaticResolver::new();
+        let mail_servers = vec![MailServer {
+            exchange: server.host(),
+            preference: 0,
+        }];
+        let emails: Vec<String> = emails.iter().map(|e| e.to_string()).collect();
+        let results =
+            verify_emails_smtp_with_retries(&emails, &mail_servers, &resolver, &config, &cache)
+                .await;
+        (server, results)
+    }
+
+    #[tokio::test]
+    async fn test_verify_against_mock_server() {
+        let script = MockSmtpScript::new()
+            .mailbox("jane@example.com", RcptBehavior::Accept)
+            .mailbox(
+                "full@example.com",
+                RcptBehavior::Reject(452, "4.2.2 Mailbox full"),
+            )
+            .mailbox(
+                "gone@example.com",
+                RcptBehavior::Reject(550, "5.2.1 Mailbox disabled"),
+            );
+        let (server, results) = verify_with_mock(
+            script,
+            &[
+                "jane@example.com",
+                "john@example.com",
+                "full@example.com",
+                "gone@example.com",
+            ],
+        )
+        .await;
+
+        let status = |email: &str| results[email].status;
+        assert_eq!(status("jane@example.com"), VerificationStatus::Valid);
+        assert_eq!(status("john@example.com"), VerificationStatus::Invalid);
+        assert_eq!(status("full@example.com"), VerificationStatus::MailboxFull);
+        assert_eq!(status("gone@example.com"), VerificationStatus::Disabled);
+        assert_eq!(
+            results["jane@example.com"].mail_server.as_deref(),
+            Some("127.0.0.1")
+        );
+        // One session: catch-all probes plus every candidate.
+        assert_eq!(server.connections(), 1);
+        assert_eq!(server.recipients().len(), 2 + 4);
+    }
+
+    #[tokio::test]
+    async fn test_verify_catch_all_mock_server() {
+        let (_server, results) =
+            verify_with_mock(MockSmtpScript::new().catch_all(), &["jane@example.com"]).await;
+        let result = &results["jane@example.com"];
+        assert_eq!(result.status, VerificationStatus::CatchAll);
+        assert_eq!(result.catch_all_confidence, Some(1.0));
+    }
+
+    #[tokio::test]
+    async fn test_verify_greylisted_mock_server() {
+        let script = MockSmtpScript::new()
+            .mailbox("jane@example.com", RcptBehavior::Greylist { attempts: 1 });
+        let (server, results) = verify_with_mock(script, &["jane@example.com"]).await;
+        let result = &results["jane@example.com"];
+        assert_eq!(result.status, VerificationStatus::Greylisted);
+        assert_eq!(result.verdict, SmtpVerdict::Greylisted);
+        // Greylisting is left to the deferred queue, not retried immediately.
+        assert_eq!(server.connections(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_verify_unresponsive_mock_servers() {
+        let dropping = MockSmtpScript::new().on_connect(ConnectBehavior::DropConnection);
+        let (_server, results) = verify_with_mock(dropping, &["jane@example.com"]).await;
+        assert_eq!(results["jane@example.com"].exists(), None);
+
+        let dropping =
+            MockSmtpScript::new().mailbox("jane@example.com", RcptBehavior::DropConnection);
+        let (_server, results) = verify_with_mock(dropping, &["jane@example.com"]).await;
+        assert_eq!(results["jane@example.com"].exists(), None);
+
+        let silent = MockSmtpScript::new().on_connect(ConnectBehavior::Stall);
+        let (_server, results) = verify_with_mock(silent, &["jane@example.com"]).await;
+        assert_eq!(
+            results["jane@example.com"].status,
+            VerificationStatus::Timeout
+        );
+
+        let stalling = MockSmtpScript::new().mailbox("jane@example.com", RcptBehavior::Stall);
+        let (_server, results) = verify_with_mock(stalling, &["jane@example.com"]).await;
+        assert_eq!(
+            results["jane@example.com"].status,
+            VerificationStatus::Timeout
+        );
     }
 }
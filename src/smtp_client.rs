@@ -0,0 +1,664 @@
+//! A minimal asynchronous SMTP client used for mailbox probing.
+//!
+//! Only the commands needed for RCPT verification are implemented
+//! (EHLO/HELO, STARTTLS, MAIL FROM, RCPT TO, RSET and QUIT). Every network
+//! operation is bounded by a timeout so a slow or tarpitting server never
+//! stalls a Tokio worker thread.
+
+use crate::error::{AppError, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{
+    AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, ReadBuf,
+};
+use tokio::net::{TcpSocket, TcpStream};
+use tokio::time::timeout;
+use tokio_rustls::TlsConnector;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use tokio_rustls::rustls::crypto::{CryptoProvider, aws_lc_rs};
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use tokio_rustls::rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+
+/// Upper bound on the length of a single reply line, to guard against
+/// misbehaving servers.
+const MAX_REPLY_LINE_LENGTH: usize = 4096;
+
+/// Upper bound on the number of lines in a multi-line reply.
+const MAX_REPLY_LINES: usize = 128;
+
+/// Reply code sent when the server is shutting down the transmission channel.
+const SERVICE_NOT_AVAILABLE: u16 = 421;
+
+/// A complete (possibly multi-line) reply received from an SMTP server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SmtpReply {
+    /// The three digit basic reply code (e.g. 250, 550).
+    pub code: u16,
+    /// The text of each reply line, without the code and separator.
+    pub lines: Vec<String>,
+}
+
+impl SmtpReply {
+    /// Returns the reply text with all lines joined by a space.
+    pub(crate) fn message(&self) -> String {
+        self.lines.join(" ")
+    }
+
+    /// True for 2xx and 3xx replies.
+    pub(crate) fn is_positive(&self) -> bool {
+        (200..400).contains(&self.code)
+    }
+
+    /// True for 2xx replies.
+    pub(crate) fn is_positive_completion(&self) -> bool {
+        (200..300).contains(&self.code)
+    }
+
+    /// True for 4xx replies.
+    pub(crate) fn is_transient_negative(&self) -> bool {
+        (400..500).contains(&self.code)
+    }
+
+    /// True for 5xx replies.
+    pub(crate) fn is_permanent_negative(&self) -> bool {
+        (500..600).contains(&self.code)
+    }
+
+    /// Returns the enhanced status code at the start of the reply text, if the
+    /// server sent one whose class matches the basic reply code.
+    pub(crate) fn enhanced_code(&self) -> Option<EnhancedStatusCode> {
+        let token = self.lines.first()?.split_whitespace().next()?;
+        EnhancedStatusCode::parse(token)
+            .filter(|enhanced| u16::from(enhanced.class) == self.code / 100)
+    }
+
+    /// True if an EHLO reply advertises the given service extension keyword.
+    ///
+    /// The first line of an EHLO reply is the server greeting, so only the
+    /// following lines are checked.
+    pub(crate) fn has_extension(&self, keyword: &str) -> bool {
+        self.lines.iter().skip(1).any(|line| {
+            line.split_whitespace()
+                .next()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case(keyword))
+        })
+    }
+}
+
+/// An RFC 3463 enhanced mail system status code, e.g. `5.1.1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct EnhancedStatusCode {
+    /// 2 (success), 4 (persistent transient failure) or 5 (permanent failure).
+    pub class: u8,
+    /// The subject, e.g. 1 for addressing or 7 for security/policy status.
+    pub subject: u16,
+    /// The detail within the subject, e.g. 1 in `5.1.1` (bad destination mailbox).
+    pub detail: u16,
+}
+
+impl EnhancedStatusCode {
+    /// Parses a `class.subject.detail` token. Returns `None` if the token is
+    /// not a well-formed enhanced status code.
+    pub(crate) fn parse(token: &str) -> Option<Self> {
+        let mut parts = token.split('.');
+        let class = parts.next()?;
+        let subject = parts.next()?;
+        let detail = parts.next()?;
+        if parts.next().is_some() {
+            return None;
+        }
+        let is_number = |part: &str, max_len: usize| {
+            !part.is_empty() && part.len() <= max_len && part.chars().all(|c| c.is_ascii_digit())
+        };
+        if !matches!(class, "2" | "4" | "5") || !is_number(subject, 3) || !is_number(detail, 3) {
+            return None;
+        }
+        Some(Self {
+            class: class.parse().ok()?,
+            subject: subject.parse().ok()?,
+            detail: detail.parse().ok()?,
+        })
+    }
+}
+
+/// Parses a single reply line into its code, the continuation flag and text.
+///
+/// Returns `None` if the line does not start with a three digit code.
+fn parse_reply_line(line: &str) -> Option<(u16, bool, &str)> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    let code_part = line.get(0..3)?;
+    if !code_part.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let code = code_part.parse::<u16>().ok()?;
+    let (is_continuation, text) = match line.as_bytes().get(3) {
+        Some(b'-') => (true, &line[4..]),
+        Some(b' ') => (false, &line[4..]),
+        None => (false, ""),
+        Some(_) => return None,
+    };
+    Some((code, is_continuation, text))
+}
+
+/// TLS settings that accept any server certificate, for opportunistic STARTTLS.
+static OPPORTUNISTIC_TLS: Lazy<Arc<ClientConfig>> = Lazy::new(|| {
+    let provider = Arc::new(aws_lc_rs::default_provider());
+    let config = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .expect("Default TLS protocol versions are supported by the crypto provider")
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(AcceptAnyCertificate(provider)))
+        .with_no_client_auth();
+    Arc::new(config)
+});
+
+/// TLS settings that require a certificate chaining to the web PKI roots.
+static VERIFIED_TLS: Lazy<Arc<ClientConfig>> = Lazy::new(|| {
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let config = ClientConfig::builder_with_provider(Arc::new(aws_lc_rs::default_provider()))
+        .with_safe_default_protocol_versions()
+        .expect("Default TLS protocol versions are supported by the crypto provider")
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Arc::new(config)
+});
+
+/// Certificate verifier that trusts any certificate but still checks the
+/// handshake signatures. Most MX hosts use self-signed or mismatched
+/// certificates, and a probe never transmits message content.
+#[derive(Debug)]
+struct AcceptAnyCertificate(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        tokio_rustls::rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        tokio_rustls::rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// The transport under an SMTP session: plaintext TCP, or TLS after STARTTLS.
+enum SmtpStream {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl AsyncRead for SmtpStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            SmtpStream::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            SmtpStream::Tls(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for SmtpStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            SmtpStream::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            SmtpStream::Tls(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            SmtpStream::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            SmtpStream::Tls(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            SmtpStream::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            SmtpStream::Tls(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
+
+/// Details of a session that was upgraded with STARTTLS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TlsSessionInfo {
+    /// Subject distinguished name of the server's leaf certificate, if one
+    /// was presented and could be parsed.
+    pub certificate_subject: Option<String>,
+}
+
+/// Extracts the subject distinguished name from a DER encoded certificate.
+fn certificate_subject(certificate: &CertificateDer<'_>) -> Option<String> {
+    x509_parser::parse_x509_certificate(certificate.as_ref())
+        .ok()
+        .map(|(_, parsed)| parsed.subject().to_string())
+}
+
+/// An open SMTP session with a mail server.
+pub(crate) struct SmtpClient {
+    stream: BufReader<SmtpStream>,
+    server: String,
+    command_timeout: Duration,
+    tls_info: Option<TlsSessionInfo>,
+}
+
+impl SmtpClient {
+    /// Connects to `server` at one of its resolved addresses and reads the
+    /// greeting.
+    ///
+    /// Every address is tried in turn until one accepts the TCP connection.
+    /// When a local address is given, only server addresses of the same family
+    /// (IPv4 or IPv6) are tried.
+    ///
+    /// # Arguments
+    /// * `server` - The hostname or IP address of the mail server, used in
+    ///   log messages and for TLS server name verification.
+    /// * `addrs` - The socket addresses `server` resolved to.
+    /// * `local_addr` - Optional local address to bind outgoing connections to.
+    /// * `command_timeout` - Timeout applied to the connection and to every command.
+    ///
+    /// # Returns
+    /// * `Ok((SmtpClient, SmtpReply))` with the open session and the server greeting.
+    /// * `Err(AppError)` if no address is usable, the connection fails or times out.
+    pub(crate) async fn connect(
+        server: &str,
+        addrs: &[SocketAddr],
+        local_addr: Option<IpAddr>,
+        command_timeout: Duration,
+    ) -> Result<(Self, SmtpReply)> {
+        let addrs: Vec<SocketAddr> = addrs
+            .iter()
+            .filter(|addr| local_addr.is_none_or(|local| local.is_ipv4() == addr.is_ipv4()))
+            .copied()
+            .collect();
+
+        if addrs.is_empty() {
+            return Err(AppError::Smtp(match local_addr {
+                Some(local) => format!(
+                    "Could not resolve a mail server address for {} reachable from {}",
+                    server, local
+                ),
+                None => format!("Could not resolve mail server address: {}", server),
+            }));
+        }
+
+        let mut last_error = None;
+        for addr in addrs {
+            tracing::debug!(target: "smtp_task", "Connecting to {} ({})", server, addr);
+            match timeout(command_timeout, Self::connect_tcp(addr, local_addr)).await {
+                Ok(Ok(tcp_stream)) => {
+                    let mut client = Self {
+                        stream: BufReader::new(SmtpStream::Plain(tcp_stream)),
+                        server: server.to_string(),
+                        command_timeout,
+                        tls_info: None,
+                    };
+                    let greeting = client.read_reply("greeting").await?;
+                    return Ok((client, greeting));
+                }
+                Ok(Err(e)) => {
+                    tracing::debug!(target: "smtp_task", "Connection to {} failed: {}", addr, e);
+                    last_error = Some(AppError::Io(e));
+                }
+                Err(_) => {
+                    tracing::debug!(target: "smtp_task", "Connection to {} timed out", addr);
+                    last_error = Some(AppError::SmtpTimeout(format!("connecting to {}", addr)));
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            AppError::Smtp(format!("Could not connect to mail server {}", server))
+        }))
+    }
+
+    /// Opens a TCP connection to `addr`, bound to `local_addr` if given.
+    async fn connect_tcp(addr: SocketAddr, local_addr: Option<IpAddr>) -> io::Result<TcpStream> {
+        let Some(local_addr) = local_addr else {
+            return TcpStream::connect(addr).await;
+        };
+        let socket = if addr.is_ipv4() {
+            TcpSocket::new_v4()?
+        } else {
+            TcpSocket::new_v6()?
+        };
+        socket.bind(SocketAddr::new(local_addr, 0))?;
+        socket.connect(addr).await
+    }
+
+    /// Sends EHLO, falling back to HELO if the server rejects EHLO.
+    pub(crate) async fn ehlo(&mut self, client_id: &str) -> Result<SmtpReply> {
+        let reply = self.command(&format!("EHLO {}", client_id)).await?;
+        if reply.is_permanent_negative() {
+            tracing::debug!(target: "smtp_task",
+                "EHLO rejected by {} ({}), falling back to HELO", self.server, reply.code
+            );
+            return self.command(&format!("HELO {}", client_id)).await;
+        }
+        Ok(reply)
+    }
+
+    /// Sends `STARTTLS` and, if the server agrees, performs the TLS handshake.
+    ///
+    /// The caller must send EHLO again afterwards, as required by RFC 3207.
+    ///
+    /// # Arguments
+    /// * `verify_certificate` - If true, the certificate must chain to a trusted
+    ///   root and be valid for the server name. Otherwise any certificate is accepted.
+    ///
+    /// # Returns
+    /// * `Ok((SmtpClient, SmtpReply))` with the session (upgraded if the reply
+    ///   was 220) and the server's reply to STARTTLS.
+    /// * `Err(AppError::SmtpTls)` if the handshake fails. The connection is
+    ///   unusable afterwards.
+    pub(crate) async fn starttls(mut self, verify_certificate: bool) -> Result<(Self, SmtpReply)> {
+        let reply = self.command("STARTTLS").await?;
+        if reply.code != 220 {
+            return Ok((self, reply));
+        }
+        if !self.stream.buffer().is_empty() {
+            // Anything pipelined before the handshake could have been injected
+            // by an attacker (CVE-2011-0411 class of bugs).
+            return Err(AppError::SmtpTls(format!(
+                "{} sent data before the TLS handshake",
+                self.server
+            )));
+        }
+
+        let tcp_stream = match self.stream.into_inner() {
+            SmtpStream::Plain(stream) => stream,
+            SmtpStream::Tls(_) => {
+                return Err(AppError::SmtpTls(format!(
+                    "Session with {} already uses TLS",
+                    self.server
+                )));
+            }
+        };
+        let server_name = ServerName::try_from(self.server.clone()).map_err(|e| {
+            AppError::SmtpTls(format!("Invalid TLS server name {}: {}", self.server, e))
+        })?;
+        let tls_config = if verify_certificate {
+            VERIFIED_TLS.clone()
+        } else {
+            OPPORTUNISTIC_TLS.clone()
+        };
+
+        let handshake = TlsConnector::from(tls_config).connect(server_name, tcp_stream);
+        let tls_stream = match timeout(self.command_timeout, handshake).await {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(e)) => {
+                return Err(AppError::SmtpTls(format!(
+                    "TLS handshake with {} failed: {}",
+                    self.server, e
+                )));
+            }
+            Err(_) => {
+                return Err(AppError::SmtpTimeout(format!(
+                    "TLS handshake with {}",
+                    self.server
+                )));
+            }
+        };
+
+        let certificate_subject = tls_stream
+            .get_ref()
+            .1
+            .peer_certificates()
+            .and_then(|chain| chain.first())
+            .and_then(certificate_subject);
+        tracing::debug!(target: "smtp_task",
+            "STARTTLS established with {} (certificate subject: {:?})",
+            self.server, certificate_subject
+        );
+
+        let client = Self {
+            stream: BufReader::new(SmtpStream::Tls(Box::new(tls_stream))),
+            server: self.server,
+            command_timeout: self.command_timeout,
+            tls_info: Some(TlsSessionInfo {
+                certificate_subject,
+            }),
+        };
+        Ok((client, reply))
+    }
+
+    /// Returns the TLS details if the session was upgraded with STARTTLS.
+    pub(crate) fn tls_info(&self) -> Option<&TlsSessionInfo> {
+        self.tls_info.as_ref()
+    }
+
+    /// Sends `MAIL FROM:<sender>`.
+    pub(crate) async fn mail_from(&mut self, sender: &str) -> Result<SmtpReply> {
+        self.command(&format!("MAIL FROM:<{}>", sender)).await
+    }
+
+    /// Sends `RCPT TO:<recipient>`.
+    pub(crate) async fn rcpt_to(&mut self, recipient: &str) -> Result<SmtpReply> {
+        self.command(&format!("RCPT TO:<{}>", recipient)).await
+    }
+
+    /// Sends `RSET` to abort the current mail transaction.
+    pub(crate) async fn rset(&mut self) -> Result<SmtpReply> {
+        self.command("RSET").await
+    }
+
+    /// Sends `QUIT` and shuts down the connection. Errors are logged and ignored,
+    /// since the probe result is already known at this point.
+    pub(crate) async fn quit(mut self) {
+        if let Err(e) = self.command("QUIT").await {
+            tracing::debug!(target: "smtp_task", "Error during SMTP QUIT on {}: {}", self.server, e);
+        }
+        let _ = self.stream.get_mut().shutdown().await;
+    }
+
+    /// Writes a command line and reads the server's reply.
+    async fn command(&mut self, command: &str) -> Result<SmtpReply> {
+        let verb = command.split_whitespace().next().unwrap_or(command);
+        tracing::trace!(target: "smtp_task", "C: {}", command);
+
+        let line = format!("{}\r\n", command);
+        let write = async {
+            let stream = self.stream.get_mut();
+            stream.write_all(line.as_bytes()).await?;
+            stream.flush().await
+        };
+        match timeout(self.command_timeout, write).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => return Err(AppError::Io(e)),
+            Err(_) => {
+                return Err(AppError::SmtpTimeout(format!(
+                    "sending {} to {}",
+                    verb, self.server
+                )));
+            }
+        }
+
+        self.read_reply(verb).await
+    }
+
+    /// Reads a complete reply, following `NNN-` continuation lines.
+    ///
+    /// A 421 reply means the server is closing the channel, so it is returned
+    /// as an `AppError::SmtpCommand` rather than as a regular reply.
+    async fn read_reply(&mut self, context: &str) -> Result<SmtpReply> {
+        let reply = match timeout(self.command_timeout, self.read_reply_lines()).await {
+            Ok(result) => result?,
+            Err(_) => {
+                return Err(AppError::SmtpTimeout(format!(
+                    "waiting for {} reply from {}",
+                    context, self.server
+                )));
+            }
+        };
+        if reply.code == SERVICE_NOT_AVAILABLE {
+            return Err(AppError::SmtpCommand {
+                code: reply.code,
+                message: reply.message(),
+            });
+        }
+        Ok(reply)
+    }
+
+    async fn read_reply_lines(&mut self) -> Result<SmtpReply> {
+        let mut lines = Vec::new();
+        loop {
+            let mut raw = String::new();
+            let read = (&mut self.stream)
+                .take(MAX_REPLY_LINE_LENGTH as u64)
+                .read_line(&mut raw)
+                .await?;
+            if read == 0 {
+                return Err(AppError::Smtp(format!(
+                    "Connection closed unexpectedly by {}",
+                    self.server
+                )));
+            }
+            tracing::trace!(target: "smtp_task", "S: {}", raw.trim_end());
+
+            let (code, is_continuation, text) = parse_reply_line(&raw).ok_or_else(|| {
+                AppError::Smtp(format!(
+                    "Malformed SMTP reply from {}: {:?}",
+                    self.server,
+                    raw.trim_end()
+                ))
+            })?;
+            lines.push(text.to_string());
+
+            if !is_continuation {
+                return Ok(SmtpReply { code, lines });
+            }
+            if lines.len() >= MAX_REPLY_LINES {
+                return Err(AppError::Smtp(format!(
+                    "SMTP reply from {} exceeded {} lines",
+                    self.server, MAX_REPLY_LINES
+                )));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reply_line() {
+        assert_eq!(
+            parse_reply_line("250-mx.example.com Hello\r\n"),
+            Some((250, true, "mx.example.com Hello"))
+        );
+        assert_eq!(
+            parse_reply_line("550 5.1.1 User unknown\r\n"),
+            Some((550, false, "5.1.1 User unknown"))
+        );
+        assert_eq!(parse_reply_line("220\r\n"), Some((220, false, "")));
+    }
+
+    #[test]
+    fn test_enhanced_code() {
+        let reply = |code: u16, text: &str| SmtpReply {
+            code,
+            lines: vec![text.to_string()],
+        };
+        assert_eq!(
+            reply(550, "5.1.1 <x@example.com>: Recipient address rejected").enhanced_code(),
+            Some(EnhancedStatusCode {
+                class: 5,
+                subject: 1,
+                detail: 1
+            })
+        );
+        assert_eq!(
+            reply(452, "4.2.2 Mailbox full").enhanced_code(),
+            Some(EnhancedStatusCode {
+                class: 4,
+                subject: 2,
+                detail: 2
+            })
+        );
+        // Class must match the basic reply code.
+        assert_eq!(reply(550, "4.2.2 Mailbox full").enhanced_code(), None);
+        assert_eq!(
+            reply(550, "Requested action not taken").enhanced_code(),
+            None
+        );
+        assert_eq!(reply(550, "5.1 Bad").enhanced_code(), None);
+        assert_eq!(reply(550, "5.1.1.1 Bad").enhanced_code(), None);
+    }
+
+    #[test]
+    fn test_has_extension() {
+        let reply = SmtpReply {
+            code: 250,
+            lines: vec![
+                "mx.example.com Hello".to_string(),
+                "PIPELINING".to_string(),
+                "SIZE 35882577".to_string(),
+                "starttls".to_string(),
+            ],
+        };
+        assert!(reply.has_extension("STARTTLS"));
+        assert!(reply.has_extension("SIZE"));
+        assert!(!reply.has_extension("mx.example.com"));
+        assert!(!reply.has_extension("8BITMIME"));
+    }
+
+    #[test]
+    fn test_parse_reply_line_invalid() {
+        assert_eq!(parse_reply_line("hello\r\n"), None);
+        assert_eq!(parse_reply_line("25"), None);
+        assert_eq!(parse_reply_line("250x nope"), None);
+    }
+}